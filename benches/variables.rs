@@ -0,0 +1,65 @@
+//! Variable lookup and string passing in the tree-walking interpreter.
+//!
+//! Run with `cargo bench --bench variables`.
+use criterion::{criterion_group, criterion_main, Criterion};
+use lox::interpreter::Interpreter;
+use lox::lex::Scanner;
+use lox::parse::{prepare, Parser, Stmt};
+
+fn parse(source: &str) -> Vec<Stmt> {
+    prepare(Parser::new(Scanner::new(source)).parse().unwrap())
+}
+
+fn run(stmts: &[Stmt]) {
+    let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+    interp.interpret(stmts).unwrap();
+}
+
+/// Reads of globals from inside nested scopes, each of which defines its own
+/// variables.
+const LOOKUPS: &str = "
+var total = 0;
+var step = 1;
+for (var i = 0; i < 2000; i = i + 1) {
+  var a = i;
+  {
+    var b = a;
+    {
+      var c = b;
+      total = total + step + c - b;
+    }
+  }
+}";
+
+/// Copies of a long string between variables.
+const STRINGS: &str = r#"
+var s = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+var t;
+for (var i = 0; i < 2000; i = i + 1) {
+  var u = s;
+  t = u;
+  s = t;
+}"#;
+
+/// The Fibonacci loop from the interpreter tests, run over locals.
+const FIBONACCI: &str = "
+for (var n = 0; n < 100; n = n + 1) {
+  var a = 0;
+  var temp;
+  for (var b = 1; a < 10000; b = temp + b) {
+    temp = a;
+    a = b;
+  }
+}";
+
+fn variables(c: &mut Criterion) {
+    let lookups = parse(LOOKUPS);
+    c.bench_function("nested lookups", |b| b.iter(|| run(&lookups)));
+    let strings = parse(STRINGS);
+    c.bench_function("string copies", |b| b.iter(|| run(&strings)));
+    let fibonacci = parse(FIBONACCI);
+    c.bench_function("fibonacci loop", |b| b.iter(|| run(&fibonacci)));
+}
+
+criterion_group!(benches, variables);
+criterion_main!(benches);
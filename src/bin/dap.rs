@@ -0,0 +1,7 @@
+use anyhow::Result as AnyResult;
+
+/// Serve a Debug Adapter Protocol session over stdin and stdout.
+fn main() -> AnyResult<()> {
+    lox::debug::dap::serve(std::io::stdin().lock(), std::io::stdout())?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use anyhow::Result as AnyResult;
+use lox::lsp::{parse_error, read_message, write_message, Server};
+
+/// Serve LSP requests over stdin and stdout until the client exits.
+fn main() -> AnyResult<()> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stdout().lock();
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        let replies = match message {
+            Ok(message) => server.handle(message),
+            Err(err) => vec![parse_error(&err)],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    std::process::exit(server.exit_code());
+}
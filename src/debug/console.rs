@@ -0,0 +1,314 @@
+use super::{Debugger, Step};
+use crate::interpreter::{Hook, Interpreter, RuntimeError};
+use crate::lex::Scanner;
+use crate::parse::{Expr, Parser, Stmt, Symbol};
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\
+break N, b N       pause before statements on line N
+delete N, d N      remove the breakpoint on line N
+step, s            run to the next statement
+next, n            run to the next statement, stepping over nested ones
+out, o             run until the enclosing statement is done
+continue, c        run to the next breakpoint
+print EXPR, p EXPR show the value of an expression
+set NAME = EXPR    change a variable
+vars, v            show the variables in every scope
+watch EXPR, w EXPR show an expression at every pause
+unwatch N          remove watch N
+list, l            show the source around the current line
+quit, q            stop the program";
+
+/// A command-line debugger, reading commands from `input` and writing to
+/// `output` whenever the program pauses.
+pub struct Console<R, W> {
+    debugger: Debugger,
+    source: Vec<String>,
+    watches: Vec<(String, Expr)>,
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Self {
+            debugger: Debugger::new(),
+            source: source.lines().map(str::to_owned).collect(),
+            watches: Vec::new(),
+            input,
+            output,
+        }
+    }
+
+    fn show_line(&mut self, line: usize) -> std::io::Result<()> {
+        let text = self.source.get(line - 1).map_or("", |text| text.trim());
+        writeln!(self.output, "line {line}: {text}")
+    }
+
+    fn show_watches(&mut self, interpreter: &mut Interpreter) -> std::io::Result<()> {
+        for (idx, (text, expr)) in self.watches.iter().enumerate() {
+            match interpreter.expression(expr) {
+                Ok(value) => writeln!(self.output, "watch {idx}: {text} = {}", value.repr())?,
+                Err(err) => writeln!(self.output, "watch {idx}: {text}: {err}")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn show_vars(&mut self, interpreter: &Interpreter) -> std::io::Result<()> {
+        let frames: Vec<_> = interpreter.environment().frames().collect();
+        for (idx, variables) in frames.iter().enumerate() {
+            let vars: Vec<String> = variables
+                .iter()
+                .map(|(name, value)| format!("{name} = {}", value.repr()))
+                .collect();
+            if idx + 1 == frames.len() {
+                write!(self.output, "globals:")?;
+            } else {
+                write!(self.output, "scope {idx}:")?;
+            }
+            if vars.is_empty() {
+                writeln!(self.output, " (empty)")?;
+            } else {
+                writeln!(self.output, " {}", vars.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn list(&mut self, line: usize) -> std::io::Result<()> {
+        let first = line.saturating_sub(3).max(1);
+        let last = (line + 2).min(self.source.len());
+        for number in first..=last {
+            let marker = if number == line { ">" } else { " " };
+            writeln!(
+                self.output,
+                "{marker}{number:4} {}",
+                self.source[number - 1]
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read and run commands until one resumes execution.
+    fn pause(
+        &mut self,
+        interpreter: &mut Interpreter,
+        line: usize,
+    ) -> std::io::Result<Option<Step>> {
+        self.show_line(line)?;
+        self.show_watches(interpreter)?;
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                // Nobody is left to drive the debugger, so let the program finish.
+                writeln!(self.output)?;
+                self.debugger.clear_breakpoints();
+                return Ok(Some(Step::Continue));
+            }
+            let (name, arg) = command
+                .trim()
+                .split_once(' ')
+                .map_or((command.trim(), ""), |(name, arg)| (name, arg.trim()));
+            match name {
+                "" => (),
+                "step" | "s" => return Ok(Some(Step::In)),
+                "next" | "n" => return Ok(Some(Step::Over)),
+                "out" | "o" => return Ok(Some(Step::Out)),
+                "continue" | "c" => return Ok(Some(Step::Continue)),
+                "quit" | "q" => return Ok(None),
+                "break" | "b" => match arg.parse() {
+                    Ok(line) => {
+                        self.debugger.add_breakpoint(line);
+                        writeln!(self.output, "Breakpoint at line {line}")?;
+                    }
+                    Err(_) => writeln!(self.output, "Expected a line number")?,
+                },
+                "delete" | "d" => match arg.parse() {
+                    Ok(line) if self.debugger.remove_breakpoint(line) => {
+                        writeln!(self.output, "Removed breakpoint at line {line}")?
+                    }
+                    Ok(line) => writeln!(self.output, "No breakpoint at line {line}")?,
+                    Err(_) => writeln!(self.output, "Expected a line number")?,
+                },
+                "print" | "p" => match parse_expression(arg) {
+                    Ok(expr) => match interpreter.expression(&expr) {
+                        Ok(value) => writeln!(self.output, "{}", value.repr())?,
+                        Err(err) => writeln!(self.output, "{err}")?,
+                    },
+                    Err(err) => writeln!(self.output, "{err}")?,
+                },
+                "set" => self.set(interpreter, arg)?,
+                "vars" | "v" => self.show_vars(interpreter)?,
+                "watch" | "w" => match parse_expression(arg) {
+                    Ok(expr) => {
+                        self.watches.push((arg.to_owned(), expr));
+                        self.show_watches(interpreter)?;
+                    }
+                    Err(err) => writeln!(self.output, "{err}")?,
+                },
+                "unwatch" => match arg.parse::<usize>() {
+                    Ok(idx) if idx < self.watches.len() => {
+                        self.watches.remove(idx);
+                    }
+                    _ => writeln!(self.output, "No watch {arg}")?,
+                },
+                "list" | "l" => self.list(line)?,
+                "help" | "h" => writeln!(self.output, "{HELP}")?,
+                _ => writeln!(self.output, "Unknown command {name:?}, try \"help\"")?,
+            }
+        }
+    }
+
+    fn set(&mut self, interpreter: &mut Interpreter, arg: &str) -> std::io::Result<()> {
+        let Some((name, value)) = arg.split_once('=') else {
+            return writeln!(self.output, "Expected NAME = EXPR");
+        };
+        let name = name.trim();
+        let result = parse_expression(value)
+            .and_then(|expr| interpreter.expression(&expr).map_err(|err| err.to_string()))
+            .and_then(|value| {
+                interpreter
+                    .environment_mut()
+                    .assign(&Symbol::intern(name), value.clone())
+                    .map(|_| value)
+                    .map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(value) => writeln!(self.output, "{name} = {}", value.repr()),
+            Err(err) => writeln!(self.output, "{err}"),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for Console<R, W> {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        depth: usize,
+    ) -> Result<(), RuntimeError> {
+        if !self.debugger.should_pause(stmt, depth) {
+            return Ok(());
+        }
+        match self
+            .pause(interpreter, stmt.line)
+            .map_err(RuntimeError::output)?
+        {
+            Some(step) => {
+                self.debugger.resume(step, depth);
+                Ok(())
+            }
+            None => Err(RuntimeError::Aborted),
+        }
+    }
+}
+
+fn parse_expression(source: &str) -> Result<Expr, String> {
+    Parser::new(Scanner::new(source))
+        .expression()
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lox, LoxError};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Captured(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Debug `source` with the given commands, returning the program's and
+    /// the debugger's output.
+    fn debug(source: &str, commands: &str) -> (Result<(), LoxError>, String) {
+        let output = Captured::default();
+        let mut lox = Lox::with_output(Box::new(output.clone()));
+        let input = std::io::Cursor::new(commands.to_owned());
+        let console = Console::new(source, input, output.clone());
+        lox.set_hook(Box::new(console));
+        let result = lox.run(source);
+        let text = String::from_utf8(output.0.take()).unwrap();
+        (result, text.replace("(debug) ", ""))
+    }
+
+    const SCRIPT: &str = "var a = 1;
+{
+  var b = a + 1;
+  print b;
+}
+print a;";
+
+    #[test]
+    fn test_step_and_inspect() {
+        let (result, output) = debug(SCRIPT, "s\ns\nvars\np b * 10\nc\n");
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            "line 1: var a = 1;
+line 3: var b = a + 1;
+line 4: print b;
+scope 0: b = 2
+globals: a = 1
+20
+2
+1
+"
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_and_next() {
+        let (_, output) = debug(SCRIPT, "b 4\nc\nn\nn\n");
+        assert_eq!(
+            output,
+            "line 1: var a = 1;
+Breakpoint at line 4
+line 4: print b;
+2
+line 6: print a;
+1
+"
+        );
+    }
+
+    #[test]
+    fn test_set_and_watch() {
+        let (_, output) = debug(SCRIPT, "n\nn\nn\nw a * 2\nset a = a + 4\nunwatch 0\nc\n");
+        assert_eq!(
+            output,
+            "line 1: var a = 1;
+line 3: var b = a + 1;
+line 4: print b;
+2
+line 6: print a;
+watch 0: a * 2 = 2
+a = 5
+5
+"
+        );
+    }
+
+    #[test]
+    fn test_quit() {
+        let (result, output) = debug(SCRIPT, "q\n");
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::Aborted))
+        ));
+        assert_eq!(output, "line 1: var a = 1;\n");
+    }
+}
@@ -0,0 +1,431 @@
+//! A Debug Adapter Protocol server, so editors can drive the debugger.
+//!
+//! The adapter is synchronous: while the program is paused it blocks reading
+//! requests, and while it runs no requests are read.  Only one program and
+//! one thread are supported, so `threadId` is always 1 and there is a single
+//! stack frame.  Imported modules run without the debugger, so breakpoints
+//! only take effect in the launched program.
+use super::{Debugger, Step};
+use crate::interpreter::{Hook, Interpreter, RuntimeError};
+use crate::lex::Scanner;
+use crate::lsp::{read_message, write_message};
+use crate::parse::{Parser, Stmt};
+use crate::{Lox, LoxError};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Result as IoResult, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const THREAD_ID: i64 = 1;
+
+/// Serve one debugging session over `input` and `output`.
+pub fn serve<R, W>(input: R, output: W) -> IoResult<()>
+where
+    R: BufRead + 'static,
+    W: Write + 'static,
+{
+    let channel = Rc::new(RefCell::new(Channel {
+        writer: output,
+        seq: 1,
+    }));
+    let session = Rc::new(RefCell::new(Session::new(input, channel.clone())));
+
+    loop {
+        let Some(request) = session.borrow_mut().next_request()? else {
+            return Ok(());
+        };
+        match session.borrow_mut().handle(&request, None)? {
+            Action::Stay | Action::Resume(_) => (),
+            Action::Start => break,
+            Action::Disconnect => return Ok(()),
+        }
+    }
+
+    let (program, source) = {
+        let mut session = session.borrow_mut();
+        if !session.stop_on_entry {
+            session.debugger.resume(Step::Continue, 0);
+        }
+        (session.program.clone(), session.source.clone())
+    };
+    let mut lox = Lox::with_output(Box::new(OutputEvents {
+        channel: channel.clone(),
+        line: Vec::new(),
+    }));
+    lox.set_hook(Box::new(SessionHook(session.clone())));
+    let result = lox
+        .set_script_path(&program)
+        .and_then(|()| lox.run(&source));
+    drop(lox);
+
+    let exit_code = match result {
+        Ok(()) => 0,
+        // The client disconnected while the program was paused.
+        Err(LoxError::Runtime(RuntimeError::Aborted)) => return Ok(()),
+        Err(err) => {
+            let body = json!({"category": "stderr", "output": format!("{err}\n")});
+            channel.borrow_mut().event("output", body)?;
+            1
+        }
+    };
+    channel.borrow_mut().event("terminated", json!({}))?;
+    channel
+        .borrow_mut()
+        .event("exited", json!({ "exitCode": exit_code }))?;
+
+    let mut session = session.borrow_mut();
+    while let Some(request) = session.next_request()? {
+        if let Action::Disconnect = session.handle(&request, None)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Numbers and writes outgoing messages.
+struct Channel<W> {
+    writer: W,
+    seq: i64,
+}
+
+impl<W: Write> Channel<W> {
+    fn send(&mut self, mut message: Value) -> IoResult<()> {
+        message["seq"] = json!(self.seq);
+        self.seq += 1;
+        write_message(&mut self.writer, &message)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> IoResult<()> {
+        self.send(json!({"type": "event", "event": event, "body": body}))
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> IoResult<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> IoResult<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+}
+
+/// Sends the program's output to the client, a line at a time.
+struct OutputEvents<W> {
+    channel: Rc<RefCell<Channel<W>>>,
+    line: Vec<u8>,
+}
+
+impl<W: Write> Write for OutputEvents<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.line.extend_from_slice(buf);
+        if self.line.ends_with(b"\n") {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        let output = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        let body = json!({"category": "stdout", "output": output});
+        self.channel.borrow_mut().event("output", body)
+    }
+}
+
+enum Action {
+    Stay,
+    Start,
+    Resume(Step),
+    Disconnect,
+}
+
+struct Session<R, W> {
+    input: R,
+    channel: Rc<RefCell<Channel<W>>>,
+    debugger: Debugger,
+    program: String,
+    source: String,
+    // Breakpoint lines by source, as set by the client.
+    breakpoints: HashMap<PathBuf, Vec<usize>>,
+    stop_on_entry: bool,
+    started: bool,
+    // Line of the statement the program is paused at.
+    line: usize,
+}
+
+impl<R: BufRead, W: Write> Session<R, W> {
+    fn new(input: R, channel: Rc<RefCell<Channel<W>>>) -> Self {
+        Self {
+            input,
+            channel,
+            debugger: Debugger::new(),
+            program: String::new(),
+            source: String::new(),
+            breakpoints: HashMap::new(),
+            stop_on_entry: false,
+            started: false,
+            line: 0,
+        }
+    }
+
+    /// The next request from the client, skipping any other messages and
+    /// any that aren't valid JSON.
+    fn next_request(&mut self) -> IoResult<Option<Value>> {
+        while let Some(message) = read_message(&mut self.input)? {
+            match message {
+                Ok(message) if message["type"] == "request" => return Ok(Some(message)),
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Stop before `stmt` and answer requests until the client resumes.
+    /// Returns None if the client disconnects.
+    fn pause(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        depth: usize,
+    ) -> IoResult<Option<Step>> {
+        let reason = if !self.started && self.stop_on_entry {
+            "entry"
+        } else if self.debugger.breakpoints().any(|line| line == stmt.line) {
+            "breakpoint"
+        } else {
+            "step"
+        };
+        self.started = true;
+        self.line = stmt.line;
+        let body = json!({"reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true});
+        self.channel.borrow_mut().event("stopped", body)?;
+
+        while let Some(request) = self.next_request()? {
+            match self.handle(&request, Some(&mut *interpreter))? {
+                Action::Stay | Action::Start => (),
+                Action::Resume(step) => {
+                    self.debugger.resume(step, depth);
+                    return Ok(Some(step));
+                }
+                Action::Disconnect => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Answer a request.  Requests about program state need `interpreter`,
+    /// which is only available while the program is paused.
+    fn handle(
+        &mut self,
+        request: &Value,
+        interpreter: Option<&mut Interpreter>,
+    ) -> IoResult<Action> {
+        let args = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+        let mut channel = self.channel.borrow_mut();
+        let action = match (command, interpreter) {
+            ("initialize", _) => {
+                let capabilities = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "exceptionBreakpointFilters": [],
+                });
+                channel.respond(request, capabilities)?;
+                channel.event("initialized", json!({}))?;
+                Action::Stay
+            }
+            ("launch", _) => {
+                let program = args["program"].as_str().unwrap_or_default();
+                match std::fs::read_to_string(program) {
+                    Ok(source) => {
+                        self.program = program.to_owned();
+                        self.source = source;
+                        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                        // Breakpoints may have been set before the launch.
+                        let lines = self.breakpoints.get(&source_key(program));
+                        self.debugger.clear_breakpoints();
+                        for line in lines.into_iter().flatten() {
+                            self.debugger.add_breakpoint(*line);
+                        }
+                        channel.respond(request, json!({}))?;
+                    }
+                    Err(err) => channel.fail(request, &format!("Can't read {program}: {err}"))?,
+                }
+                Action::Stay
+            }
+            ("setBreakpoints", _) => {
+                let path = args["source"]["path"].as_str().unwrap_or_default();
+                let lines: Vec<usize> = args["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|bp| bp["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect();
+                let key = source_key(path);
+                let verified = !self.program.is_empty() && key == source_key(&self.program);
+                if verified {
+                    self.debugger.clear_breakpoints();
+                    for line in &lines {
+                        self.debugger.add_breakpoint(*line);
+                    }
+                }
+                let breakpoints: Vec<Value> = lines
+                    .iter()
+                    .map(|line| json!({"verified": verified, "line": line}))
+                    .collect();
+                self.breakpoints.insert(key, lines);
+                channel.respond(request, json!({ "breakpoints": breakpoints }))?;
+                Action::Stay
+            }
+            ("setExceptionBreakpoints", _) => {
+                // No filters are advertised, so there is nothing to set.
+                channel.respond(request, json!({}))?;
+                Action::Stay
+            }
+            ("configurationDone", _) => {
+                channel.respond(request, json!({}))?;
+                Action::Start
+            }
+            ("threads", _) => {
+                let threads = json!({"threads": [{"id": THREAD_ID, "name": "main"}]});
+                channel.respond(request, threads)?;
+                Action::Stay
+            }
+            ("disconnect" | "terminate", _) => {
+                channel.respond(request, json!({}))?;
+                Action::Disconnect
+            }
+            ("stackTrace", Some(_)) => {
+                let frame = json!({
+                    "id": 0,
+                    "name": "main",
+                    "line": self.line,
+                    "column": 1,
+                    "source": {"name": self.program, "path": self.program},
+                });
+                channel.respond(request, json!({"stackFrames": [frame], "totalFrames": 1}))?;
+                Action::Stay
+            }
+            ("scopes", Some(interpreter)) => {
+                let count = interpreter.environment().frames().count();
+                let scopes: Vec<Value> = (0..count)
+                    .map(|idx| {
+                        let name = if idx + 1 == count { "Globals" } else { "Block" };
+                        json!({"name": name, "variablesReference": idx + 1, "expensive": false})
+                    })
+                    .collect();
+                channel.respond(request, json!({ "scopes": scopes }))?;
+                Action::Stay
+            }
+            ("variables", Some(interpreter)) => {
+                // References are 1-based indexes of scopes, innermost first.
+                let reference = args["variablesReference"].as_u64().unwrap_or_default() as usize;
+                let frame = interpreter
+                    .environment()
+                    .frames()
+                    .nth(reference.wrapping_sub(1));
+                match frame {
+                    Some(frame) => {
+                        let variables: Vec<Value> = frame
+                            .iter()
+                            .map(|(name, value)| {
+                                json!({
+                                    "name": name,
+                                    "value": value.repr().to_string(),
+                                    "variablesReference": 0,
+                                })
+                            })
+                            .collect();
+                        channel.respond(request, json!({ "variables": variables }))?
+                    }
+                    None => channel.fail(request, "Unknown variables reference")?,
+                }
+                Action::Stay
+            }
+            ("evaluate", Some(interpreter)) => {
+                let expression = args["expression"].as_str().unwrap_or_default();
+                let result = Parser::new(Scanner::new(expression))
+                    .expression()
+                    .map_err(|err| err.to_string())
+                    .and_then(|expr| interpreter.expression(&expr).map_err(|err| err.to_string()));
+                match result {
+                    Ok(value) => {
+                        let body =
+                            json!({"result": value.repr().to_string(), "variablesReference": 0});
+                        channel.respond(request, body)?
+                    }
+                    Err(err) => channel.fail(request, &err)?,
+                }
+                Action::Stay
+            }
+            ("continue" | "next" | "stepIn" | "stepOut", Some(_)) => {
+                let step = match command {
+                    "continue" => Step::Continue,
+                    "next" => Step::Over,
+                    "stepIn" => Step::In,
+                    _ => Step::Out,
+                };
+                channel.respond(request, json!({"allThreadsContinued": true}))?;
+                Action::Resume(step)
+            }
+            (
+                "stackTrace" | "scopes" | "variables" | "evaluate" | "continue" | "next" | "stepIn"
+                | "stepOut",
+                None,
+            ) => {
+                channel.fail(request, "The program is not paused")?;
+                Action::Stay
+            }
+            _ => {
+                channel.fail(request, &format!("Unsupported request {command:?}"))?;
+                Action::Stay
+            }
+        };
+        Ok(action)
+    }
+}
+
+/// Identifies a source file however the client spells its path.
+fn source_key(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Lets the interpreter pause a session that is shared with `serve`.
+struct SessionHook<R, W>(Rc<RefCell<Session<R, W>>>);
+
+impl<R: BufRead, W: Write> Hook for SessionHook<R, W> {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        depth: usize,
+    ) -> Result<(), RuntimeError> {
+        let mut session = self.0.borrow_mut();
+        if !session.debugger.should_pause(stmt, depth) {
+            return Ok(());
+        }
+        match session
+            .pause(interpreter, stmt, depth)
+            .map_err(RuntimeError::output)?
+        {
+            Some(_) => Ok(()),
+            None => Err(RuntimeError::Aborted),
+        }
+    }
+}
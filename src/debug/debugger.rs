@@ -0,0 +1,129 @@
+use crate::parse::{Stmt, StmtKind};
+use std::collections::BTreeSet;
+
+/// How to continue after a pause.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Run until a breakpoint.
+    Continue,
+    /// Pause at the very next statement.
+    In,
+    /// Pause at the next statement that isn't inside the current one.
+    Over,
+    /// Pause at the next statement outside the one enclosing the current one.
+    Out,
+}
+
+/// Decides where execution pauses, independently of how the user drives it.
+///
+/// Breakpoints are by line: execution pauses before every statement that
+/// starts on a breakpoint line.  Blocks never pause by themselves, since the
+/// statements inside them do.
+#[derive(Debug)]
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    step: Step,
+    // Depth of the statement that was running when stepping began.
+    depth: usize,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    /// A debugger that pauses before the first statement.
+    pub fn new() -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            step: Step::In,
+            depth: 0,
+        }
+    }
+
+    /// Add a breakpoint, returning false if there already was one.
+    pub fn add_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.insert(line)
+    }
+
+    /// Remove a breakpoint, returning false if there wasn't one.
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Resume from a pause at a statement of the given depth.
+    pub fn resume(&mut self, step: Step, depth: usize) {
+        self.step = step;
+        self.depth = depth;
+    }
+
+    /// Whether execution should pause before `stmt`.
+    pub fn should_pause(&mut self, stmt: &Stmt, depth: usize) -> bool {
+        // Once the statement being stepped over or out of is done, pause at
+        // the next statement wherever it is.
+        match self.step {
+            Step::Over if depth <= self.depth => self.step = Step::In,
+            Step::Out if depth < self.depth => self.step = Step::In,
+            _ => (),
+        }
+        if matches!(stmt.kind, StmtKind::Block(_)) {
+            return false;
+        }
+        self.step == Step::In || self.breakpoints.contains(&stmt.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Expr;
+
+    fn print(line: usize) -> Stmt {
+        Stmt::print(line, Expr::number(1.))
+    }
+
+    #[test]
+    fn test_stepping() {
+        let mut debugger = Debugger::new();
+        assert!(debugger.should_pause(&print(1), 0));
+        assert!(!debugger.should_pause(&Stmt::block(1, vec![]), 0));
+
+        debugger.resume(Step::Over, 1);
+        assert!(!debugger.should_pause(&print(2), 2));
+        assert!(debugger.should_pause(&print(3), 1));
+
+        debugger.resume(Step::Out, 1);
+        assert!(!debugger.should_pause(&print(2), 1));
+        assert!(debugger.should_pause(&print(3), 0));
+
+        // Stepping over a block pauses inside the statement after it.
+        debugger.resume(Step::Over, 0);
+        assert!(!debugger.should_pause(&Stmt::block(2, vec![]), 0));
+        assert!(debugger.should_pause(&print(3), 1));
+
+        debugger.resume(Step::Continue, 0);
+        assert!(!debugger.should_pause(&print(1), 0));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new();
+        debugger.resume(Step::Continue, 0);
+        assert!(debugger.add_breakpoint(2));
+        assert!(!debugger.add_breakpoint(2));
+        assert!(debugger.should_pause(&print(2), 3));
+        assert!(!debugger.should_pause(&print(3), 3));
+        assert!(debugger.remove_breakpoint(2));
+        assert!(!debugger.should_pause(&print(2), 3));
+    }
+}
@@ -0,0 +1,7 @@
+//! Source-level debugging on top of the interpreter's statement hook.
+mod console;
+pub mod dap;
+mod debugger;
+
+pub use console::Console;
+pub use debugger::{Debugger, Step};
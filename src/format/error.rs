@@ -0,0 +1,10 @@
+use crate::parse::ParseError;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FormatError {
+    #[error("Unable to format invalid source: {0}")]
+    Parse(#[from] ParseError),
+    #[error("Formatting changed the meaning of the program.")]
+    Unstable,
+}
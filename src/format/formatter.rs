@@ -0,0 +1,356 @@
+use super::{FormatError, FormatResult};
+use crate::lex::{Scanner, Token, TokenType, TokenType::*};
+use crate::parse::visit::{walk_stmt_mut, VisitorMut};
+use crate::parse::{Parser, Stmt};
+
+const INDENT: &str = "  ";
+
+/// Re-emit `source` in canonical Lox style, keeping comments and blank-line
+/// grouping.
+///
+/// The output is built from the token stream, so comments and surface syntax
+/// like `for` loops survive.  The source is parsed first so invalid programs
+/// are rejected, and the output is parsed again to check that formatting
+/// did not change the program.
+pub fn format_source(source: &str) -> FormatResult<String> {
+    let original = parse(source)?;
+    let formatted = format_tokens(source);
+    // Output that no longer parses is as much our fault as a changed tree.
+    if parse(&formatted).ok() != Some(original) {
+        return Err(FormatError::Unstable);
+    }
+    Ok(formatted)
+}
+
+fn format_tokens(source: &str) -> String {
+    let mut formatter = Formatter::new();
+    for token in Scanner::new(source) {
+        formatter.token(token);
+    }
+    formatter.finish()
+}
+
+/// Parse `source`, with line numbers erased since formatting moves statements.
+fn parse(source: &str) -> FormatResult<Vec<Stmt>> {
+    let mut stmts = Parser::new(Scanner::new(source)).parse()?;
+    stmts.iter_mut().for_each(erase_lines);
+    Ok(stmts)
+}
+
+fn erase_lines(stmt: &mut Stmt) {
+    EraseLines.visit_stmt_mut(stmt);
+}
+
+struct EraseLines;
+
+impl VisitorMut for EraseLines {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        stmt.line = 0;
+        walk_stmt_mut(self, stmt);
+    }
+}
+
+struct Formatter<'a> {
+    out: String,
+    indent: usize,
+    // Semicolons inside parentheses (ie `for` headers) don't end a line.
+    paren_depth: usize,
+    // The next token starts a new line.
+    newline_pending: bool,
+    prev: Option<Token<'a>>,
+    // The source line the previous token ended on.
+    prev_end_line: usize,
+    // Whether the previous token was a prefix operator.
+    prev_unary: bool,
+    // Whether the previous token ended an operand, making a following `-`
+    // binary and a following `++` postfix.
+    prev_operand: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new() -> Self {
+        Self {
+            out: String::new(),
+            indent: 0,
+            paren_depth: 0,
+            newline_pending: false,
+            prev: None,
+            prev_end_line: 0,
+            prev_unary: false,
+            prev_operand: false,
+        }
+    }
+
+    fn token(&mut self, token: Token<'a>) {
+        let prev_typ = self.prev.as_ref().map(|t| t.typ);
+        let mut unary = false;
+        let mut operand = ends_operand(token.typ);
+        match token.typ {
+            Eof => return,
+            Comment | DocComment => {
+                if prev_typ.is_some() && token.line == self.prev_end_line {
+                    // Trailing comment: keep it on the line it annotates.
+                    self.out.push(' ');
+                } else {
+                    self.start_line(&token);
+                }
+                self.out.push_str(token.lexeme.trim_end());
+                // Code can carry on after a block comment.
+                self.newline_pending = !token.lexeme.starts_with("/*");
+            }
+            LeftBrace => {
+                if self.newline_pending {
+                    self.start_line(&token);
+                } else if prev_typ.is_some() {
+                    self.out.push(' ');
+                }
+                self.out.push('{');
+                self.indent += 1;
+                self.newline_pending = true;
+            }
+            RightBrace => {
+                self.indent = self.indent.saturating_sub(1);
+                if prev_typ == Some(LeftBrace) {
+                    // Empty blocks stay on one line.
+                    self.newline_pending = false;
+                } else {
+                    self.start_line(&token);
+                }
+                self.out.push('}');
+                self.newline_pending = true;
+            }
+            Semicolon => {
+                // Only a line comment leaves a newline pending before `;`.
+                if self.newline_pending {
+                    self.start_line(&token);
+                }
+                self.out.push(';');
+                self.newline_pending = self.paren_depth == 0;
+            }
+            Else | Catch | Finally if prev_typ == Some(RightBrace) => {
+                self.newline_pending = false;
+                self.out.push(' ');
+                self.out.push_str(token.lexeme);
+            }
+            typ => {
+                if self.newline_pending {
+                    self.start_line(&token);
+                } else if self.needs_space(&token) {
+                    self.out.push(' ');
+                }
+                self.push_text(&token);
+                match typ {
+                    LeftParen => self.paren_depth += 1,
+                    RightParen => self.paren_depth = self.paren_depth.saturating_sub(1),
+                    Bang | Tilde => unary = true,
+                    Minus => unary = !self.prev_operand,
+                    PlusPlus | MinusMinus => {
+                        unary = !self.prev_operand;
+                        operand = self.prev_operand;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.prev_end_line = token.line + newlines(&token);
+        self.prev_unary = unary;
+        self.prev_operand = operand;
+        self.prev = Some(token);
+    }
+
+    fn finish(mut self) -> String {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    /// Begin a new, indented line for `token`, keeping at most one blank
+    /// line of the source's grouping.
+    fn start_line(&mut self, token: &Token<'a>) {
+        self.newline_pending = false;
+        if self.out.is_empty() {
+            return;
+        }
+        self.out.push('\n');
+        let after_open = self.prev.as_ref().is_some_and(|t| t.typ == LeftBrace);
+        if token.line > self.prev_end_line + 1 && !after_open && token.typ != RightBrace {
+            self.out.push('\n');
+        }
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn needs_space(&self, token: &Token<'a>) -> bool {
+        let Some(prev) = &self.prev else {
+            return false;
+        };
+        match (prev.typ, token.typ) {
+            (_, Semicolon | RightParen | Comma | Dot) => false,
+            (LeftParen | Dot, _) => false,
+            // Interpolated expressions sit right against the `${` and `}`.
+            (Interpolation, _) => false,
+            (_, Interpolation | String_) if token.lexeme.starts_with('}') => false,
+            (Identifier, LeftParen) => false,
+            (_, PlusPlus | MinusMinus) if self.prev_operand => false,
+            // Keep `- -a` from becoming `--a`.
+            (Minus, Minus | MinusMinus) | (Plus, Plus | PlusPlus) => true,
+            _ => !self.prev_unary,
+        }
+    }
+
+    fn push_text(&mut self, token: &Token<'a>) {
+        self.out.push_str(token.lexeme);
+    }
+}
+
+/// Whether a token can end an operand, making a following `-` binary.
+fn ends_operand(typ: TokenType) -> bool {
+    matches!(
+        typ,
+        Identifier | Number | String_ | RightParen | True | False | Nil | This | Super
+    )
+}
+
+/// How many lines a token spans after its first, for strings and block
+/// comments.
+fn newlines(token: &Token<'_>) -> usize {
+    token.lexeme.matches('\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_format(source: &str, expected: &str) {
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_empty() {
+        assert_format("", "");
+        assert_format("  \n\n", "");
+    }
+
+    #[test]
+    fn test_format_spacing() {
+        assert_format("print 1+2*  3;", "print 1 + 2 * 3;\n");
+        assert_format("var a=-1;print -a- -a;", "var a = -1;\nprint -a - -a;\n");
+        assert_format("print !(true)==false;", "print !(true) == false;\n");
+        assert_format(r#"print "a"+"b c";"#, "print \"a\" + \"b c\";\n");
+        assert_format("a ++;-- b;x+=a++ - -b;", "a++;\n--b;\nx += a++ - -b;\n");
+        assert_format("print - -a;", "print - -a;\n");
+        assert_format("print - --a;", "print - --a;\n");
+        assert_format("print -(--a);", "print -(--a);\n");
+    }
+
+    #[test]
+    fn test_format_blocks() {
+        assert_format(
+            "{var a=1;{print a;}}",
+            "{\n  var a = 1;\n  {\n    print a;\n  }\n}\n",
+        );
+        assert_format("{}", "{}\n");
+        assert_format(
+            "if (a) {print 1;} else {print 2;}",
+            "if (a) {\n  print 1;\n} else {\n  print 2;\n}\n",
+        );
+        assert_format(
+            "if(a)print 1;else print 2;",
+            "if (a) print 1;\nelse print 2;\n",
+        );
+        assert_format(
+            "try{throw e.message;}catch(e){}\nfinally{}",
+            "try {\n  throw e.message;\n} catch (e) {} finally {}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_for() {
+        assert_format(
+            "for(var i=0;i<3;i=i+1){print i;}",
+            "for (var i = 0; i < 3; i = i + 1) {\n  print i;\n}\n",
+        );
+        assert_format("for(;;)print 1;", "for (;;) print 1;\n");
+    }
+
+    #[test]
+    fn test_format_comments() {
+        assert_format(
+            "// header\nvar a = 1;   // trailing   \n{\n// inside\nprint a;}\n",
+            "// header\nvar a = 1; // trailing\n{\n  // inside\n  print a;\n}\n",
+        );
+        assert_format(
+            "/// Doc.\nvar a = /* one\n  two */ 1;\nprint a;",
+            "/// Doc.\nvar a = /* one\n  two */ 1;\nprint a;\n",
+        );
+    }
+
+    #[test]
+    fn test_format_blank_lines() {
+        assert_format(
+            "var a = 1;\n\n\n\nvar b = 2;\nvar c = 3;\n{\n\nprint a;\n\n}\n",
+            "var a = 1;\n\nvar b = 2;\nvar c = 3;\n{\n  print a;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_multiline_string() {
+        assert_format(
+            "var s = \"one\ntwo\";\n\nprint s;",
+            "var s = \"one\ntwo\";\n\nprint s;\n",
+        );
+        // Strings keep their escapes and quoting as written.
+        assert_format(
+            r#"print "${ a+1 } and ${"${ -b }"}";"#,
+            "print \"${a + 1} and ${\"${-b}\"}\";\n",
+        );
+        assert_format(
+            "print \"a\\tb\"+\"\"\"raw \\n\nline\"\"\";",
+            "print \"a\\tb\" + \"\"\"raw \\n\nline\"\"\";\n",
+        );
+    }
+
+    #[test]
+    fn test_format_comment_before_punctuation() {
+        assert_format("print 1 // x\n;", "print 1 // x\n;\n");
+        assert_format("print (1 // x\n);", "print (1 // x\n);\n");
+        // Lox has no commas yet, so check the token stream directly.
+        assert_eq!(format_tokens("f(1 // x\n, 2);"), "f(1 // x\n, 2);\n");
+    }
+
+    #[test]
+    fn test_format_invalid() {
+        assert!(matches!(
+            format_source("print (1;"),
+            Err(FormatError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_conformance_corpus() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox");
+        let mut dirs = vec![root];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                // Scripts that are expected not to parse can't be formatted.
+                if let Ok(formatted) = format_source(&source) {
+                    assert_eq!(
+                        format_source(&formatted).unwrap(),
+                        formatted,
+                        "Formatting {path:?} is not idempotent"
+                    );
+                }
+            }
+        }
+    }
+}
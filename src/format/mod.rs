@@ -0,0 +1,7 @@
+mod error;
+mod formatter;
+
+pub use error::FormatError;
+pub use formatter::format_source;
+
+type FormatResult<T> = Result<T, FormatError>;
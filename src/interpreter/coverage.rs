@@ -0,0 +1,250 @@
+use crate::parse::visit::{walk_expr, walk_stmt, Visitor};
+use crate::parse::{Expr, Stmt, StmtKind};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// A two-way branch: an `if` statement, or a logical operator that may
+/// short-circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Branch {
+    pub line: usize,
+    /// Times the `then` branch (`if`) or the short-circuit (`and`/`or`) was
+    /// taken, followed by times the other branch was.
+    pub taken: [u64; 2],
+}
+
+/// Which statements and branches of a program have run.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Lines that start a statement, with how many statements on the line ran.
+    pub lines: BTreeMap<usize, u64>,
+    /// Branches in source order.
+    pub branches: Vec<Branch>,
+    // Branches of the program being run, by the address of their node.
+    index: HashMap<usize, usize>,
+}
+
+/// Identifies an AST node while the program containing it is running.
+pub fn node_id<T>(node: &T) -> usize {
+    node as *const T as usize
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the statements and branches of a program about to run, so that
+    /// the ones that never run are reported.
+    pub fn register(&mut self, stmts: &[Stmt]) {
+        // Earlier programs are finished, so their addresses may be reused.
+        self.index.clear();
+        let mut register = Register {
+            coverage: self,
+            line: 0,
+        };
+        for stmt in stmts {
+            register.visit_stmt(stmt);
+        }
+    }
+
+    fn register_branch(&mut self, id: usize, line: usize) {
+        self.index.insert(id, self.branches.len());
+        self.branches.push(Branch {
+            line,
+            taken: [0, 0],
+        });
+    }
+
+    pub fn hit_line(&mut self, line: usize) {
+        *self.lines.entry(line).or_default() += 1;
+    }
+
+    /// Record that `branch` (0 or 1) of the node `id` was taken.  Branches of
+    /// nodes that weren't registered are ignored.
+    pub fn hit_branch(&mut self, id: usize, branch: usize) {
+        if let Some(idx) = self.index.get(&id) {
+            self.branches[*idx].taken[branch] += 1;
+        }
+    }
+
+    /// The coverage of the file at `path` as an LCOV tracefile record.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut out = format!("TN:\nSF:{path}\n");
+        let mut block = 0;
+        let mut last_line = 0;
+        for branch in &self.branches {
+            // Blocks are numbered from 0 within each line.
+            block = if branch.line == last_line {
+                block + 1
+            } else {
+                0
+            };
+            last_line = branch.line;
+            let line_ran = self.lines.get(&branch.line).is_some_and(|count| *count > 0);
+            for (idx, taken) in branch.taken.iter().enumerate() {
+                if line_ran {
+                    let _ = writeln!(out, "BRDA:{},{block},{idx},{taken}", branch.line);
+                } else {
+                    let _ = writeln!(out, "BRDA:{},{block},{idx},-", branch.line);
+                }
+            }
+        }
+        let (branches_hit, branches_found) = self.branch_totals();
+        let _ = write!(out, "BRF:{branches_found}\nBRH:{branches_hit}\n");
+        for (line, count) in &self.lines {
+            let _ = writeln!(out, "DA:{line},{count}");
+        }
+        let (lines_hit, lines_found) = self.line_totals();
+        let _ = write!(out, "LF:{lines_found}\nLH:{lines_hit}\nend_of_record\n");
+        out
+    }
+
+    /// A one-line summary for the terminal, followed by the lines that never
+    /// ran, if any.
+    pub fn summary(&self, path: &str) -> String {
+        let (lines_hit, lines_found) = self.line_totals();
+        let (branches_hit, branches_found) = self.branch_totals();
+        let mut out = format!(
+            "{path}: lines {lines_hit}/{lines_found} ({}), branches {branches_hit}/{branches_found} ({})\n",
+            percent(lines_hit, lines_found),
+            percent(branches_hit, branches_found),
+        );
+        let missed: Vec<String> = self
+            .lines
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(line, _)| line.to_string())
+            .collect();
+        if !missed.is_empty() {
+            let _ = writeln!(out, "  not run: {}", missed.join(", "));
+        }
+        out
+    }
+
+    fn line_totals(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|count| **count > 0).count();
+        (hit, self.lines.len())
+    }
+
+    fn branch_totals(&self) -> (usize, usize) {
+        let taken = self.branches.iter().flat_map(|b| b.taken);
+        let hit = taken.filter(|count| *count > 0).count();
+        (hit, self.branches.len() * 2)
+    }
+}
+
+/// Adds the lines and branches of a program to `coverage`.
+struct Register<'a> {
+    coverage: &'a mut Coverage,
+    // Line of the statement being registered, for its expressions.
+    line: usize,
+}
+
+impl Visitor for Register<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.coverage.lines.entry(stmt.line).or_default();
+        if let StmtKind::If { .. } = stmt.kind {
+            self.coverage.register_branch(node_id(stmt), stmt.line);
+        }
+        let outer = std::mem::replace(&mut self.line, stmt.line);
+        walk_stmt(self, stmt);
+        self.line = outer;
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Logical { .. } | Expr::Conditional { .. } = expr {
+            self.coverage.register_branch(node_id(expr), self.line);
+        }
+        walk_expr(self, expr);
+    }
+}
+
+fn percent(hit: usize, found: usize) -> String {
+    if found == 0 {
+        "-".to_owned()
+    } else {
+        format!("{:.1}%", hit as f64 * 100. / found as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::lex::Scanner;
+    use crate::parse::{lower::lower, Parser};
+
+    fn coverage(source: &str) -> Coverage {
+        let stmts = lower(Parser::new(Scanner::new(source)).parse().unwrap());
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.enable_coverage();
+        interp.interpret(&stmts).unwrap();
+        interp.take_coverage().unwrap()
+    }
+
+    const SOURCE: &str = "var a = 1;
+if (a > 2) {
+  print \"big\";
+} else {
+  print a or nil;
+}
+for (var i = 0; i < 2; i = i + 1) print i;
+var b = a and false;";
+
+    #[test]
+    fn test_lines() {
+        let coverage = coverage(SOURCE);
+        let lines: Vec<(usize, u64)> = coverage.lines.into_iter().collect();
+        // The lowered `for` runs a block, initializer and loop on line 7,
+        // then a block holding the body and increment twice.
+        assert_eq!(
+            lines,
+            vec![(1, 1), (2, 1), (3, 0), (4, 1), (5, 1), (7, 9), (8, 1)]
+        );
+    }
+
+    #[test]
+    fn test_branches() {
+        let coverage = coverage(SOURCE);
+        let branches: Vec<(usize, [u64; 2])> = coverage
+            .branches
+            .iter()
+            .map(|b| (b.line, b.taken))
+            .collect();
+        assert_eq!(branches, vec![(2, [0, 1]), (5, [1, 0]), (8, [0, 1])]);
+    }
+
+    #[test]
+    fn test_lcov() {
+        let lcov =
+            coverage("var a = true;\nif (a) print 1;\nif (!a) {\n  print a or 2;\n}").lcov("t.lox");
+        assert_eq!(
+            lcov,
+            "TN:
+SF:t.lox
+BRDA:2,0,0,1
+BRDA:2,0,1,0
+BRDA:3,0,0,0
+BRDA:3,0,1,1
+BRDA:4,0,0,-
+BRDA:4,0,1,-
+BRF:6
+BRH:2
+DA:1,1
+DA:2,2
+DA:3,1
+DA:4,0
+LF:4
+LH:3
+end_of_record
+"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = coverage("if (false) print 1;").summary("t.lox");
+        assert_eq!(summary, "t.lox: lines 1/1 (100.0%), branches 1/2 (50.0%)\n");
+    }
+}
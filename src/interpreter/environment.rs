@@ -1,53 +1,201 @@
 use super::{RuntimeError, RuntimeResult, Value};
-use std::collections::HashMap;
+use crate::parse::{Slot, Symbol, SymbolMap};
 
+/// The variables of a running program: globals by name, and the locals of
+/// each enclosing block on a stack.
+#[derive(Default)]
 pub struct Environment {
-    // A None enclosing means this is the top-level non-global scope
-    enclosing: Option<Box<Environment>>,
     // Uninitialized variables (eg from `var x;`) are stored as Nil
-    values: HashMap<String, Value>,
+    globals: SymbolMap<Value>,
+    // Locals of every enclosing block, outermost first
+    locals: Vec<Value>,
+    // The name of each local, for lookups that weren't resolved
+    names: Vec<Symbol>,
+    // Where each block's locals start in `locals`, innermost last
+    scopes: Vec<usize>,
 }
 
 impl Environment {
     pub fn new() -> Self {
-        Environment {
-            enclosing: None,
-            values: HashMap::new(),
+        Self::default()
+    }
+
+    /// Enter a block.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(self.locals.len());
+    }
+
+    /// Leave a block, dropping its locals.
+    pub fn pop_scope(&mut self) {
+        let start = self
+            .scopes
+            .pop()
+            .expect("Attempted to pop a global environment");
+        self.locals.truncate(start);
+        self.names.truncate(start);
+    }
+
+    /// Declare `name` in the innermost scope.  Redeclaring a name in the
+    /// same scope reuses its slot, as `resolve` expects.
+    pub fn define(&mut self, name: Symbol, value: Value) {
+        let Some(start) = self.scopes.last() else {
+            self.globals.insert(name, value);
+            return;
+        };
+        match self.names[*start..].iter().position(|n| *n == name) {
+            Some(index) => self.locals[start + index] = value,
+            None => {
+                self.locals.push(value);
+                self.names.push(name);
+            }
         }
     }
 
-    pub fn with(enclosing: Environment) -> Self {
-        Environment {
-            enclosing: Some(Box::new(enclosing)),
-            values: HashMap::new(),
+    /// Look up `name` in each enclosing scope.
+    pub fn get(&self, name: &Symbol) -> RuntimeResult<&Value> {
+        match self.names.iter().rposition(|n| n == name) {
+            Some(idx) => Ok(&self.locals[idx]),
+            None => self.get_global(name),
         }
     }
 
-    pub fn define(&mut self, name: &str, value: Value) {
-        self.values.insert(name.to_owned(), value);
+    pub fn assign(&mut self, name: &Symbol, value: Value) -> RuntimeResult<()> {
+        match self.names.iter().rposition(|n| n == name) {
+            Some(idx) => {
+                self.locals[idx] = value;
+                Ok(())
+            }
+            None => self.assign_global(name, value),
+        }
+    }
+
+    /// Look up a variable where `resolve` found it to be.
+    pub fn get_slot(&self, name: &Symbol, slot: Slot) -> RuntimeResult<&Value> {
+        match slot {
+            Slot::Dynamic => self.get(name),
+            Slot::Local { depth, index } => Ok(&self.locals[self.local(depth, index)]),
+            Slot::Global => self.get_global(name),
+        }
     }
 
-    pub fn get(&self, name: &str) -> RuntimeResult<&Value> {
-        match (self.values.get(name), &self.enclosing) {
-            (Some(val), _) => Ok(val),
-            (None, Some(env)) => env.get(name),
-            (None, None) => Err(RuntimeError::unbound_var(name)),
+    pub fn assign_slot(&mut self, name: &Symbol, slot: Slot, value: Value) -> RuntimeResult<()> {
+        match slot {
+            Slot::Dynamic => self.assign(name, value),
+            Slot::Local { depth, index } => {
+                let idx = self.local(depth, index);
+                self.locals[idx] = value;
+                Ok(())
+            }
+            Slot::Global => self.assign_global(name, value),
         }
     }
 
-    pub fn assign(&mut self, name: &str, value: Value) -> RuntimeResult<()> {
-        match (self.values.contains_key(name), &mut self.enclosing) {
-            (true, _) => {
-                self.values.insert(name.to_owned(), value);
+    fn local(&self, depth: usize, index: usize) -> usize {
+        self.scopes[self.scopes.len() - 1 - depth] + index
+    }
+
+    fn get_global(&self, name: &Symbol) -> RuntimeResult<&Value> {
+        self.globals
+            .get(name)
+            .ok_or_else(|| RuntimeError::unbound_var(name.as_str()))
+    }
+
+    fn assign_global(&mut self, name: &Symbol, value: Value) -> RuntimeResult<()> {
+        match self.globals.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
                 Ok(())
             }
-            (false, Some(env)) => env.assign(&name, value),
-            (false, None) => Err(RuntimeError::unbound_var(name)),
+            None => Err(RuntimeError::unbound_var(name.as_str())),
         }
     }
 
-    /// Drop this environment and return the enclosing environment.
-    pub fn pop(self) -> Option<Self> {
-        self.enclosing.map(|e| *e)
+    /// The global variables, eg to export them from a module.
+    pub fn into_globals(self) -> SymbolMap<Value> {
+        self.globals
+    }
+
+    /// The variables of each scope sorted by name, from the innermost block
+    /// out to the globals.
+    pub fn frames(&self) -> impl Iterator<Item = Vec<(&str, &Value)>> {
+        let ends = self
+            .scopes
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.locals.len()]);
+        let blocks: Vec<Vec<(&str, &Value)>> = self
+            .scopes
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| {
+                let names = self.names[*start..end].iter().map(Symbol::as_str);
+                sorted(names.zip(&self.locals[*start..end]).collect())
+            })
+            .collect();
+        let globals = self.globals.iter().map(|(n, v)| (n.as_str(), v)).collect();
+        blocks.into_iter().rev().chain([sorted(globals)])
+    }
+}
+
+fn sorted<'a>(mut variables: Vec<(&'a str, &'a Value)>) -> Vec<(&'a str, &'a Value)> {
+    variables.sort_by_key(|(name, _)| *name);
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+
+    #[test]
+    fn test_scopes() {
+        let mut env = Environment::new();
+        env.define(sym("a"), Value::Number(1.));
+        env.push_scope();
+        env.define(sym("b"), Value::Number(2.));
+        env.push_scope();
+        env.define(sym("a"), Value::Number(3.));
+        env.define(sym("c"), Value::Nil);
+
+        assert_eq!(env.get(&sym("a")), Ok(&Value::Number(3.)));
+        let local = Slot::Local { depth: 1, index: 0 };
+        assert_eq!(env.get_slot(&sym("b"), local), Ok(&Value::Number(2.)));
+        assert_eq!(
+            env.get_slot(&sym("a"), Slot::Global),
+            Ok(&Value::Number(1.))
+        );
+        env.assign_slot(&sym("b"), local, Value::Bool(true))
+            .unwrap();
+
+        let frames: Vec<_> = env.frames().collect();
+        assert_eq!(
+            frames,
+            vec![
+                vec![("a", &Value::Number(3.)), ("c", &Value::Nil)],
+                vec![("b", &Value::Bool(true))],
+                vec![("a", &Value::Number(1.))],
+            ]
+        );
+
+        env.pop_scope();
+        assert_eq!(env.get(&sym("a")), Ok(&Value::Number(1.)));
+        assert!(env.get(&sym("c")).is_err());
+        env.pop_scope();
+        assert!(env.get(&sym("b")).is_err());
+    }
+
+    #[test]
+    fn test_redefine() {
+        let mut env = Environment::new();
+        env.push_scope();
+        env.define(sym("a"), Value::Number(1.));
+        env.define(sym("a"), Value::Number(2.));
+        let slot = Slot::Local { depth: 0, index: 0 };
+        assert_eq!(env.get_slot(&sym("a"), slot), Ok(&Value::Number(2.)));
+        assert_eq!(env.frames().next().map(|f| f.len()), Some(1));
     }
 }
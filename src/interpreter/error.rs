@@ -6,6 +6,31 @@ pub enum RuntimeError {
     TypeError { expected: String },
     #[error("Unbound variable: {name}.")]
     UnboundVar { name: String },
+    #[error("Interrupted.")]
+    Interrupted,
+    #[error("Aborted.")]
+    Aborted,
+    #[error("Unable to write output: {message}.")]
+    Output { message: String },
+    #[error("Undefined property: {name}.")]
+    UndefinedProperty { name: String },
+    /// A `throw` that no `catch` handled.  The thrown value itself is kept
+    /// by the interpreter, so that this stays `Send`.
+    #[error("Uncaught exception on line {line}: {message}.")]
+    Thrown { message: String, line: usize },
+    #[error("Unable to import \"{path}\": {message}.")]
+    Import { path: String, message: String },
+    #[error("Cyclic import of \"{path}\".")]
+    CyclicImport { path: String },
+    /// The host ran a program that wasn't passed through `parse::prepare`.
+    #[error("Unable to run a `for` loop that hasn't been lowered.")]
+    Unlowered,
+    /// An error raised while running the module imported as `path`.
+    #[error("In module \"{path}\": {error}")]
+    InModule {
+        path: String,
+        error: Box<RuntimeError>,
+    },
 }
 
 impl RuntimeError {
@@ -17,4 +42,45 @@ impl RuntimeError {
     pub fn unbound_var(name: impl Into<String>) -> Self {
         RuntimeError::UnboundVar { name: name.into() }
     }
+    pub fn output(err: std::io::Error) -> Self {
+        RuntimeError::Output {
+            message: err.to_string(),
+        }
+    }
+    pub fn undefined_property(name: impl Into<String>) -> Self {
+        RuntimeError::UndefinedProperty { name: name.into() }
+    }
+    /// `path` couldn't be found, read or parsed because of `err`.
+    pub fn import(path: &str, err: impl std::fmt::Display) -> Self {
+        RuntimeError::Import {
+            path: path.to_owned(),
+            message: err.to_string().trim_end_matches('.').to_owned(),
+        }
+    }
+
+    /// Whether a `catch` clause handles this error.  Interrupts and host
+    /// failures always reach the host.
+    pub fn is_catchable(&self) -> bool {
+        match self {
+            RuntimeError::TypeError { .. }
+            | RuntimeError::UnboundVar { .. }
+            | RuntimeError::UndefinedProperty { .. }
+            | RuntimeError::Thrown { .. }
+            | RuntimeError::Import { .. }
+            | RuntimeError::CyclicImport { .. } => true,
+            RuntimeError::Interrupted
+            | RuntimeError::Aborted
+            | RuntimeError::Output { .. }
+            | RuntimeError::Unlowered => false,
+            RuntimeError::InModule { error, .. } => error.is_catchable(),
+        }
+    }
+
+    /// The error a module raised, looking through any imports it came from.
+    pub fn innermost(&self) -> &RuntimeError {
+        match self {
+            RuntimeError::InModule { error, .. } => error.innermost(),
+            err => err,
+        }
+    }
 }
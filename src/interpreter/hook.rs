@@ -0,0 +1,18 @@
+use super::{Interpreter, RuntimeError};
+use crate::parse::Stmt;
+
+/// Observes execution, for tools like debuggers.
+///
+/// The hook is detached from the interpreter while it runs, so it is free to
+/// evaluate expressions or change variables through the interpreter it is
+/// given.
+pub trait Hook {
+    /// Called before each statement runs.  `depth` is how many statements
+    /// enclose this one.  Returning an error aborts the program with it.
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        depth: usize,
+    ) -> Result<(), RuntimeError>;
+}
@@ -1,31 +1,147 @@
-use super::Value;
-use super::{Environment, RuntimeError, RuntimeResult};
-use crate::parse::{BinaryOp, Expr, LogicalOp, Stmt, UnaryOp};
+use super::coverage::{node_id, Coverage};
+use super::profile::{Profile, Profiler};
+use super::{Environment, Hook, InterruptHandle, RuntimeError, RuntimeResult};
+use super::{ErrorValue, FileLoader, Module, ModuleLoader, Value};
+use crate::lex::Scanner;
+use crate::parse::{prepare, BinaryOp, Expr, LValue, LogicalOp, Parser, Stmt, StmtKind, UnaryOp};
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
 
 pub struct Interpreter {
     env: Environment,
+    interrupt: InterruptHandle,
+    // Destination of `print` statements.
+    output: Box<dyn Write>,
+    hook: Option<Box<dyn Hook>>,
+    // Number of statements enclosing the one being executed.
+    depth: usize,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    // Line of the statement being executed, kept after it fails so that a
+    // `catch` can report where.
+    line: usize,
+    // The value of the `throw` being unwound, which `RuntimeError::Thrown`
+    // only describes.
+    thrown: Option<Value>,
+    loader: Box<dyn ModuleLoader>,
+    // Every module imported so far, by canonical name.
+    modules: HashMap<String, Rc<Module>>,
+    // The canonical names of the modules being imported, innermost last.
+    importing: Vec<String>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    /// An interpreter whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
         Self {
             env: Environment::new(),
+            interrupt: InterruptHandle::new(),
+            output,
+            hook: None,
+            depth: 0,
+            profiler: None,
+            coverage: None,
+            line: 0,
+            thrown: None,
+            loader: Box::new(FileLoader::default()),
+            modules: HashMap::new(),
+            importing: Vec::new(),
+        }
+    }
+
+    /// Find imported modules with `loader`.  Modules already imported stay
+    /// cached.
+    pub fn set_module_loader(&mut self, loader: Box<dyn ModuleLoader>) {
+        self.loader = loader;
+    }
+
+    /// Run the main program as the module the loader names `path`, so that
+    /// its imports are found next to it and importing it again is a cycle.
+    /// Call this after `set_module_loader`.
+    pub fn set_main_module(&mut self, path: &str) -> RuntimeResult<()> {
+        let name = self
+            .loader
+            .resolve(path, None)
+            .map_err(|err| RuntimeError::import(path, err))?;
+        self.importing = vec![name];
+        Ok(())
+    }
+
+    /// Record which statements and branches of each program run.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    /// Stop recording coverage and return what was recorded.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    fn cover_branch<T>(&mut self, node: &T, branch: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit_branch(node_id(node), branch);
         }
     }
 
-    fn push_env(&mut self) {
-        let old_env = std::mem::replace(&mut self.env, Environment::new());
-        self.env = Environment::with(old_env);
+    /// Record how often and for how long each line and construct runs.
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
     }
 
-    fn pop_env(&mut self) {
-        let old_env = std::mem::replace(&mut self.env, Environment::new());
-        self.env = old_env
-            .pop()
-            .expect("Attempted to pop a global environment");
+    /// Stop profiling and return what was recorded.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profiler.take().map(Profiler::finish)
     }
 
+    /// Call `hook` before every statement from now on.
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hook = Some(hook);
+    }
+
+    pub fn take_hook(&mut self) -> Option<Box<dyn Hook>> {
+        self.hook.take()
+    }
+
+    /// The innermost scope of the running program.
+    pub fn environment(&self) -> &Environment {
+        &self.env
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.env
+    }
+
+    /// A handle that aborts the current `interpret` call with
+    /// `RuntimeError::Interrupted`.  Globals defined so far are kept.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    fn check_interrupt(&self) -> RuntimeResult<()> {
+        if self.interrupt.is_interrupted() {
+            self.interrupt.reset();
+            Err(RuntimeError::Interrupted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run a program that has been through `parse::prepare`.
     pub fn interpret(&mut self, stmts: &[Stmt]) -> RuntimeResult<()> {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.register(stmts);
+        }
         for stmt in stmts {
             self.statement(stmt)?;
         }
@@ -33,36 +149,82 @@ impl Interpreter {
     }
 
     pub fn statement(&mut self, stmt: &Stmt) -> RuntimeResult<()> {
-        match stmt {
-            Stmt::Var { name, initializer } => {
+        self.run_statement(stmt, Self::execute)
+    }
+
+    /// Run `stmt` like `statement`, returning its value if it is an
+    /// expression statement, so that a REPL can echo it.
+    pub fn evaluate_statement(&mut self, stmt: &Stmt) -> RuntimeResult<Option<Value>> {
+        self.run_statement(stmt, |interp, stmt| match &stmt.kind {
+            StmtKind::Expression(expr) => interp.expression(expr).map(Some),
+            _ => interp.execute(stmt).map(|()| None),
+        })
+    }
+
+    fn run_statement<T>(
+        &mut self,
+        stmt: &Stmt,
+        run: impl FnOnce(&mut Self, &Stmt) -> RuntimeResult<T>,
+    ) -> RuntimeResult<T> {
+        self.check_interrupt()?;
+        if let Some(mut hook) = self.hook.take() {
+            let res = hook.before_statement(self, stmt, self.depth);
+            self.hook = Some(hook);
+            res?;
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit_line(stmt.line);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter_statement(stmt);
+        }
+        self.depth += 1;
+        let outer_line = std::mem::replace(&mut self.line, stmt.line);
+        let res = run(self, stmt);
+        if res.is_ok() {
+            self.line = outer_line;
+        }
+        self.depth -= 1;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        res
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> RuntimeResult<()> {
+        match &stmt.kind {
+            StmtKind::Var {
+                name, initializer, ..
+            } => {
                 let value = initializer
                     .as_ref()
                     .map(|expr| self.expression(expr))
                     .transpose()?;
-                self.env.define(name, value.unwrap_or(Value::Nil));
+                self.env.define(name.clone(), value.unwrap_or(Value::Nil));
                 Ok(())
             }
-            Stmt::Expression(expr) => self.expression(expr).map(|_| ()),
-            Stmt::If {
+            StmtKind::Expression(expr) => self.expression(expr).map(|_| ()),
+            StmtKind::If {
                 condition,
                 then_branch,
                 else_branch,
             } => {
-                if self.expression(condition)?.is_truthy() {
-                    self.statement(&then_branch)
+                let truthy = self.expression(condition)?.is_truthy();
+                self.cover_branch(stmt, if truthy { 0 } else { 1 });
+                if truthy {
+                    self.statement(then_branch)
                 } else if let Some(else_br) = else_branch {
                     self.statement(else_br)
                 } else {
                     Ok(())
                 }
             }
-            Stmt::Print(expr) => {
+            StmtKind::Print(expr) => {
                 let value = self.expression(expr)?;
-                println!("{}", value);
-                Ok(())
+                writeln!(self.output, "{value}").map_err(RuntimeError::output)
             }
-            Stmt::Block(statements) => {
-                self.push_env();
+            StmtKind::Block(statements) => {
+                self.env.push_scope();
                 let mut res: RuntimeResult<()> = Ok(());
                 for stmt in statements {
                     res = self.statement(stmt);
@@ -70,22 +232,149 @@ impl Interpreter {
                         break;
                     }
                 }
-                self.pop_env();
+                self.env.pop_scope();
                 res
             }
-            Stmt::While { condition, body } => {
+            StmtKind::While { condition, body } => {
                 while self.expression(condition)?.is_truthy() {
+                    self.check_interrupt()?;
                     self.statement(body)?;
                 }
                 Ok(())
             }
+            StmtKind::For { .. } => Err(RuntimeError::Unlowered),
+            StmtKind::Import { path, name, .. } => {
+                let module = self.import(path)?;
+                self.env.define(name.clone(), Value::Module(module));
+                Ok(())
+            }
+            StmtKind::Throw(expr) => {
+                let value = self.expression(expr)?;
+                let message = value.to_string();
+                self.thrown = Some(value);
+                Err(RuntimeError::Thrown {
+                    message,
+                    line: stmt.line,
+                })
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let mut res = self.statement(body);
+                if let (Err(err), Some((name, handler))) = (&res, catch) {
+                    if err.is_catchable() {
+                        let value = self.caught(err);
+                        self.env.push_scope();
+                        self.env.define(name.clone(), value);
+                        res = self.statement(handler);
+                        self.env.pop_scope();
+                    }
+                }
+                if let Some(finally) = finally {
+                    // Runs whatever happened above, and an error from it
+                    // replaces any still unwinding.
+                    let pending = self.thrown.take();
+                    self.statement(finally)?;
+                    self.thrown = pending;
+                }
+                res
+            }
+        }
+    }
+
+    /// The module `path` refers to, run with its own globals the first time
+    /// it is imported.
+    fn import(&mut self, path: &str) -> RuntimeResult<Rc<Module>> {
+        let importer = self.importing.last().map(String::as_str);
+        let name = self
+            .loader
+            .resolve(path, importer)
+            .map_err(|err| RuntimeError::import(path, err))?;
+        if let Some(module) = self.modules.get(&name) {
+            return Ok(Rc::clone(module));
+        }
+        if self.importing.contains(&name) {
+            return Err(RuntimeError::CyclicImport {
+                path: path.to_owned(),
+            });
+        }
+        let source = self
+            .loader
+            .load(&name)
+            .map_err(|err| RuntimeError::import(path, err))?;
+        let stmts = Parser::new(Scanner::new(&source))
+            .parse()
+            .map_err(|err| RuntimeError::import(path, err))?;
+        let stmts = prepare(stmts);
+
+        // Hooks, coverage and the profiler are keyed by the main program's
+        // lines, so they only see the main program.
+        self.importing.push(name.clone());
+        let outer_env = std::mem::take(&mut self.env);
+        let hook = self.hook.take();
+        let coverage = self.coverage.take();
+        let profiler = self.profiler.take();
+        let res = stmts.iter().try_for_each(|stmt| self.statement(stmt));
+        self.hook = hook;
+        self.coverage = coverage;
+        self.profiler = profiler;
+        let env = std::mem::replace(&mut self.env, outer_env);
+        self.importing.pop();
+        // Interrupts and host failures aren't about the module, so they
+        // pass through unchanged.
+        res.map_err(|err| match err {
+            err if err.is_catchable() => RuntimeError::InModule {
+                path: path.to_owned(),
+                error: Box::new(err),
+            },
+            err => err,
+        })?;
+
+        let module = Rc::new(Module {
+            path: path.to_owned(),
+            globals: env.into_globals(),
+        });
+        self.modules.insert(name, Rc::clone(&module));
+        Ok(module)
+    }
+
+    /// The value a `catch` clause binds for `err`: the thrown value, or an
+    /// error value describing a runtime error.
+    fn caught(&mut self, err: &RuntimeError) -> Value {
+        match (err.innermost(), self.thrown.take()) {
+            (RuntimeError::Thrown { .. }, Some(value)) => value,
+            _ => Value::Error(Rc::new(ErrorValue {
+                message: err.to_string().into(),
+                line: self.line,
+            })),
         }
     }
 
     pub fn expression(&mut self, expr: &Expr) -> RuntimeResult<Value> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter_expression(expr);
+        }
+        let res = self.evaluate(expr);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        res
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> RuntimeResult<Value> {
         match expr {
             Expr::Literal(lit) => Ok(Value::of(lit)),
             Expr::Grouping(expr) => self.expression(expr),
+            Expr::Interpolation(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    // Formatted as `print` would show it.
+                    text += &self.expression(part)?.to_string();
+                }
+                Ok(Value::String(text.into()))
+            }
             Expr::Unary { op, right } => {
                 let value = self.expression(right)?;
                 self.unary(*op, &value)
@@ -95,28 +384,90 @@ impl Interpreter {
                 let right_val = self.expression(right)?;
                 self.binary(&left_val, *op, &right_val)
             }
-            Expr::Variable(name) => self.env.get(name).map(|v| v.clone()),
-            Expr::Assign { name, expr } => {
-                let val = self.expression(expr)?;
-                self.env.assign(name, val.clone())?;
+            Expr::Variable { name, slot } => self.env.get_slot(name, *slot).cloned(),
+            Expr::Get { object, name } => {
+                let object = self.expression(object)?;
+                match (&object, name.as_str()) {
+                    (Value::Error(err), "message") => Ok(Value::String(Rc::clone(&err.message))),
+                    (Value::Error(err), "line") => Ok(Value::Number(err.line as f64)),
+                    (Value::Module(module), _) => match module.globals.get(name) {
+                        Some(value) => Ok(value.clone()),
+                        None => Err(RuntimeError::undefined_property(name.as_str())),
+                    },
+                    (Value::Error(_), _) => Err(RuntimeError::undefined_property(name.as_str())),
+                    _ => Err(RuntimeError::type_error(format!(
+                        "Only errors and modules have properties, but got {}",
+                        object.repr()
+                    ))),
+                }
+            }
+            Expr::Assign { target, op, expr } => {
+                let val = match op {
+                    None => self.expression(expr)?,
+                    Some(op) => {
+                        let current = self.get_target(target)?;
+                        let operand = self.expression(expr)?;
+                        self.binary(&current, *op, &operand)?
+                    }
+                };
+                self.set_target(target, val.clone())?;
                 Ok(val)
             }
+            Expr::Update { target, op, prefix } => {
+                let old = self.get_target(target)?;
+                let new = self.binary(&old, op.binary_op(), &Value::Number(1.))?;
+                self.set_target(target, new.clone())?;
+                Ok(if *prefix { new } else { old })
+            }
             Expr::Logical { left, op, right } => {
                 let left_val = self.expression(left)?;
                 match (left_val.is_truthy(), op) {
-                    (true, LogicalOp::Or) | (false, LogicalOp::And) => Ok(left_val),
-                    (false, LogicalOp::Or) | (true, LogicalOp::And) => self.expression(right),
+                    (true, LogicalOp::Or) | (false, LogicalOp::And) => {
+                        self.cover_branch(expr, 0);
+                        Ok(left_val)
+                    }
+                    (false, LogicalOp::Or) | (true, LogicalOp::And) => {
+                        self.cover_branch(expr, 1);
+                        self.expression(right)
+                    }
+                }
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.expression(condition)?.is_truthy() {
+                    self.cover_branch(expr, 0);
+                    self.expression(then_branch)
+                } else {
+                    self.cover_branch(expr, 1);
+                    self.expression(else_branch)
                 }
             }
         }
     }
 
+    fn get_target(&self, target: &LValue) -> RuntimeResult<Value> {
+        match target {
+            LValue::Variable { name, slot } => self.env.get_slot(name, *slot).cloned(),
+        }
+    }
+
+    fn set_target(&mut self, target: &LValue, value: Value) -> RuntimeResult<()> {
+        match target {
+            LValue::Variable { name, slot } => self.env.assign_slot(name, *slot, value),
+        }
+    }
+
     fn unary(&self, op: UnaryOp, value: &Value) -> RuntimeResult<Value> {
         match (op, value) {
             (UnaryOp::Minus, Value::Number(num)) => Ok(Value::Number(-*num)),
             (UnaryOp::Not, val) => Ok(Value::Bool(!val.is_truthy())),
+            (UnaryOp::BitNot, Value::Number(num)) => Ok(Value::Number(!integer(*num)? as f64)),
             _ => Err(RuntimeError::type_error(format!(
-                "Can't combine {op:?} and {value:?}"
+                "Can't combine {op:?} and {}",
+                value.repr()
             ))),
         }
     }
@@ -131,6 +482,30 @@ impl Interpreter {
             (Value::Number(left), BinaryOp::Div, Value::Number(right)) => {
                 Ok(Value::Number(left / right))
             }
+            (Value::Number(left), BinaryOp::Mod, Value::Number(right)) => {
+                // The remainder takes the sign of the divisor, to match `~/`.
+                let rem = left % right;
+                if rem != 0. && (rem < 0.) != (*right < 0.) {
+                    Ok(Value::Number(rem + right))
+                } else {
+                    Ok(Value::Number(rem))
+                }
+            }
+            (Value::Number(left), BinaryOp::FloorDiv, Value::Number(right)) => {
+                Ok(Value::Number((left / right).floor()))
+            }
+            (Value::Number(left), BinaryOp::Pow, Value::Number(right)) => {
+                Ok(Value::Number(left.powf(*right)))
+            }
+            (
+                Value::Number(left),
+                op @ (BinaryOp::BitAnd
+                | BinaryOp::BitOr
+                | BinaryOp::BitXor
+                | BinaryOp::ShiftLeft
+                | BinaryOp::ShiftRight),
+                Value::Number(right),
+            ) => bitwise(integer(*left)?, op, integer(*right)?).map(|n| Value::Number(n as f64)),
             (Value::Number(left), BinaryOp::Add, Value::Number(right)) => {
                 Ok(Value::Number(left + right))
             }
@@ -138,7 +513,7 @@ impl Interpreter {
                 Ok(Value::Number(left - right))
             }
             (Value::String(left), BinaryOp::Add, Value::String(right)) => {
-                Ok(Value::String(format!("{left}{right}")))
+                Ok(Value::String(format!("{left}{right}").into()))
             }
             (Value::Number(left), BinaryOp::Greater, Value::Number(right)) => {
                 Ok(Value::Bool(left > right))
@@ -153,17 +528,56 @@ impl Interpreter {
                 Ok(Value::Bool(left <= right))
             }
             _ => Err(RuntimeError::type_error(format!(
-                "Can't combine {left_val:?} and {right_val:?} with {op:?}"
+                "Can't combine {} and {} with {op:?}",
+                left_val.repr(),
+                right_val.repr()
             ))),
         }
     }
 }
 
+/// The value of an operand to a bitwise operator, which must be an integer.
+fn integer(num: f64) -> RuntimeResult<i64> {
+    if num.fract() == 0. && num.abs() < i64::MAX as f64 {
+        Ok(num as i64)
+    } else {
+        Err(RuntimeError::type_error(format!(
+            "Bitwise operands must be integers, but got {}",
+            Value::Number(num)
+        )))
+    }
+}
+
+fn bitwise(left: i64, op: BinaryOp, right: i64) -> RuntimeResult<i64> {
+    let shift = |shifted: fn(i64, u32) -> Option<i64>| {
+        u32::try_from(right)
+            .ok()
+            .and_then(|amount| shifted(left, amount))
+            .ok_or_else(|| {
+                RuntimeError::type_error(format!(
+                    "Shift amounts must be from 0 to 63, but got {right}"
+                ))
+            })
+    };
+    match op {
+        BinaryOp::BitAnd => Ok(left & right),
+        BinaryOp::BitOr => Ok(left | right),
+        BinaryOp::BitXor => Ok(left ^ right),
+        BinaryOp::ShiftLeft => shift(i64::checked_shl),
+        BinaryOp::ShiftRight => shift(i64::checked_shr),
+        _ => unreachable!("{op:?} is not bitwise"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{lex::Scanner, parse::Parser};
+    use crate::{
+        lex::Scanner,
+        parse::{lower::lower_stmt, Parser, Symbol},
+    };
 
     use super::*;
+    use crate::interpreter::MemoryLoader;
 
     fn assert_expression(source: &str, expected: RuntimeResult<Value>) {
         let mut interp = Interpreter::new();
@@ -182,7 +596,7 @@ mod tests {
     fn assert_statement(source: &str, success: bool) {
         let mut interp = Interpreter::new();
         let scanner = Scanner::new(source);
-        let ast = Parser::new(scanner).declaration().unwrap();
+        let ast = lower_stmt(Parser::new(scanner).declaration().unwrap());
         let actual = interp.statement(&ast);
         match (success, actual.is_ok()) {
             (true, true) => (),
@@ -196,7 +610,7 @@ mod tests {
     fn test_interpret_literals() {
         assert_expression("1", Ok(Value::Number(1.)));
         assert_expression("false", Ok(Value::Bool(false)));
-        assert_expression(r#""abc""#, Ok(Value::String("abc".to_owned())));
+        assert_expression(r#""abc""#, Ok(Value::String("abc".into())));
     }
 
     #[test]
@@ -219,10 +633,38 @@ mod tests {
         assert_expression("true == 1", Ok(Value::Bool(false)));
         assert_expression("2 >= 1", Ok(Value::Bool(true)));
         assert_expression("2 * 1.01", Ok(Value::Number(2.02)));
-        assert_expression(r#""a" + "b""#, Ok(Value::String("ab".to_owned())));
+        assert_expression(r#""a" + "b""#, Ok(Value::String("ab".into())));
         assert_expression("1 + false", Err(RuntimeError::type_error("")));
     }
 
+    #[test]
+    fn test_interpret_arithmetic() {
+        assert_expression("7 % 3", Ok(Value::Number(1.)));
+        assert_expression("-7 % 3", Ok(Value::Number(2.)));
+        assert_expression("7 % -3", Ok(Value::Number(-2.)));
+        assert_expression("5.5 % 2", Ok(Value::Number(1.5)));
+        assert_expression("7 ~/ 2", Ok(Value::Number(3.)));
+        assert_expression("-7 ~/ 2", Ok(Value::Number(-4.)));
+        assert_expression("-2 ** 2", Ok(Value::Number(-4.)));
+        assert_expression("2 ** 3 ** 2", Ok(Value::Number(512.)));
+        assert_expression("4 ** 0.5", Ok(Value::Number(2.)));
+    }
+
+    #[test]
+    fn test_interpret_bitwise() {
+        assert_expression("6 & 3", Ok(Value::Number(2.)));
+        assert_expression("6 | 3", Ok(Value::Number(7.)));
+        assert_expression("6 ^ 3", Ok(Value::Number(5.)));
+        assert_expression("~5", Ok(Value::Number(-6.)));
+        assert_expression("1 << 10", Ok(Value::Number(1024.)));
+        assert_expression("-16 >> 2", Ok(Value::Number(-4.)));
+        assert_expression("1.5 & 1", Err(RuntimeError::type_error("")));
+        assert_expression("~0.5", Err(RuntimeError::type_error("")));
+        assert_expression("1 << 64", Err(RuntimeError::type_error("")));
+        assert_expression("1 >> -1", Err(RuntimeError::type_error("")));
+        assert_expression("true | 1", Err(RuntimeError::type_error("")));
+    }
+
     #[test]
     fn test_interpret_complex() {
         assert_expression("2 > (2 * 1.01)", Ok(Value::Bool(false)));
@@ -289,12 +731,45 @@ mod tests {
 
     #[test]
     fn test_logical_expr() {
-        assert_expression(r#" "hi" or 2 "#, Ok(Value::String("hi".to_owned())));
+        assert_expression(r#" "hi" or 2 "#, Ok(Value::String("hi".into())));
         assert_expression(r#" "hi" and 2 "#, Ok(Value::Number(2.)));
-        assert_expression(r#" nil or "yes" "#, Ok(Value::String("yes".to_owned())));
+        assert_expression(r#" nil or "yes" "#, Ok(Value::String("yes".into())));
         assert_expression(r#" nil and "yes" "#, Ok(Value::Nil));
     }
 
+    #[test]
+    fn test_compound_assignment() {
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        let stmts = Parser::new(Scanner::new(
+            "var a = 1; { var b = a++; a *= 10; b -= ++a; a += b; }",
+        ))
+        .parse()
+        .unwrap();
+        interp.interpret(&stmts).unwrap();
+        assert_eq!(
+            interp.environment().get(&Symbol::intern("a")),
+            Ok(&Value::Number(1.))
+        );
+    }
+
+    #[test]
+    fn test_interpolation_expr() {
+        assert_expression(
+            r#" "n=${1 + 1.5}, ${nil} ${"s"}${true}" "#,
+            Ok(Value::String("n=2.5, nil strue".into())),
+        );
+        assert_expression(r#" "${-"s"}" "#, Err(RuntimeError::type_error("")));
+    }
+
+    #[test]
+    fn test_conditional_expr() {
+        assert_expression(r#" 0 ? "yes" : "no" "#, Ok(Value::String("yes".into())));
+        assert_expression(r#" nil ? "yes" : "no" "#, Ok(Value::String("no".into())));
+        // Only the chosen branch is evaluated.
+        assert_expression("true ? 1 : -nil", Ok(Value::Number(1.)));
+        assert_expression("false ? -nil : 2", Ok(Value::Number(2.)));
+    }
+
     #[test]
     fn test_loop_stmt() {
         assert_statement("var going = true; while (going) going = false;", true);
@@ -311,4 +786,207 @@ mod tests {
             true,
         );
     }
+
+    fn run(interp: &mut Interpreter, source: &str) -> RuntimeResult<()> {
+        let stmts = Parser::new(Scanner::new(source)).parse().unwrap();
+        interp.interpret(&prepare(stmts))
+    }
+
+    fn global(interp: &Interpreter, name: &str) -> Value {
+        interp
+            .environment()
+            .get(&Symbol::intern(name))
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        let source = "var a; var b; var c;
+            try {
+                a = -nil;
+            } catch (e) {
+                a = e;
+                try { throw 1; } catch (e) { b = e; }
+            }
+            try { try { throw \"x\"; } finally { c = 2; } } catch (e) { c += e.message; }";
+        assert_eq!(
+            run(&mut interp, source),
+            Err(RuntimeError::type_error(
+                "Only errors and modules have properties, but got \"x\""
+            ))
+        );
+        let Value::Error(err) = global(&interp, "a") else {
+            panic!("Expected an error value");
+        };
+        assert!(err.message.starts_with("Type error:"));
+        assert_eq!(err.line, 3);
+        assert_eq!(global(&interp, "b"), Value::Number(1.));
+        assert_eq!(global(&interp, "c"), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_uncaught_throw() {
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        let source = "var a = 0;\ntry { throw \"up\"; } finally { a = 1; }";
+        assert_eq!(
+            run(&mut interp, source),
+            Err(RuntimeError::Thrown {
+                message: "up".to_owned(),
+                line: 2,
+            })
+        );
+        assert_eq!(global(&interp, "a"), Value::Number(1.));
+    }
+
+    #[test]
+    fn test_interrupt_not_caught() {
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        let handle = interp.interrupt_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            handle.interrupt();
+        });
+        let source = "var a = 0; try { while (true) {} } catch (e) { a = 1; } finally { a += 2; }";
+        assert_eq!(run(&mut interp, source), Err(RuntimeError::Interrupted));
+        canceller.join().unwrap();
+        // Only the `finally` ran.
+        assert_eq!(global(&interp, "a"), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_import() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("util/math.lox", "var calls = 0; var two = 2;");
+        loader.insert(
+            "util/twice.lox",
+            "import \"math.lox\"; var four = math.two * 2;",
+        );
+        loader.insert("a.lox", "import \"b.lox\";");
+        loader.insert("b.lox", "import \"a.lox\";");
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.set_module_loader(Box::new(loader));
+
+        let source = "import \"util/twice.lox\";
+            import \"util/math.lox\" as m;
+            var four = twice.four;
+            var shared = twice.math == m;";
+        assert_eq!(run(&mut interp, source), Ok(()));
+        assert_eq!(global(&interp, "four"), Value::Number(4.));
+        assert_eq!(global(&interp, "shared"), Value::Bool(true));
+        // Module globals stay in the module.
+        assert!(interp.environment().get(&Symbol::intern("two")).is_err());
+
+        let err = run(&mut interp, "import \"a.lox\";").unwrap_err();
+        assert_eq!(
+            err.innermost(),
+            &RuntimeError::CyclicImport {
+                path: "a.lox".to_owned()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "In module \"a.lox\": In module \"b.lox\": Cyclic import of \"a.lox\"."
+        );
+        // The main program's globals are back after a failed import.
+        assert_eq!(global(&interp, "four"), Value::Number(4.));
+    }
+
+    #[test]
+    fn test_import_main_module() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("main.lox", "");
+        loader.insert("lib.lox", "import \"main.lox\";");
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.set_module_loader(Box::new(loader));
+        assert!(interp.set_main_module("missing.lox").is_err());
+        interp.set_main_module("main.lox").unwrap();
+        assert_eq!(
+            run(&mut interp, "import \"main.lox\";"),
+            Err(RuntimeError::CyclicImport {
+                path: "main.lox".to_owned()
+            })
+        );
+        assert_eq!(
+            run(&mut interp, "import \"lib.lox\";"),
+            Err(RuntimeError::InModule {
+                path: "lib.lox".to_owned(),
+                error: Box::new(RuntimeError::CyclicImport {
+                    path: "main.lox".to_owned()
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_module_errors() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("throws.lox", "throw \"oops\";");
+        loader.insert("loops.lox", "while (true) {}");
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.set_module_loader(Box::new(loader));
+        // The importer catches the thrown value itself.
+        let source = "var caught; try { import \"throws.lox\"; } catch (e) { caught = e; }";
+        assert_eq!(run(&mut interp, source), Ok(()));
+        assert_eq!(global(&interp, "caught"), Value::String("oops".into()));
+
+        interp.interrupt_handle().interrupt();
+        assert_eq!(
+            run(&mut interp, "import \"loops.lox\";"),
+            Err(RuntimeError::Interrupted)
+        );
+    }
+
+    #[test]
+    fn test_interrupt_before_run() {
+        let mut interp = Interpreter::new();
+        let ast = Parser::new(Scanner::new("while (true) {}"))
+            .declaration()
+            .unwrap();
+        interp.interrupt_handle().interrupt();
+        assert_eq!(interp.statement(&ast), Err(RuntimeError::Interrupted));
+        // The interrupt is consumed, so the next statement runs normally.
+        let ast = Parser::new(Scanner::new("print 1;")).declaration().unwrap();
+        assert_eq!(interp.statement(&ast), Ok(()));
+    }
+
+    #[test]
+    fn test_unlowered_for() {
+        let source = "var n = 0; for (var i = 0; i < 3; i++) n += i;";
+        let stmts = Parser::new(Scanner::new(source)).parse().unwrap();
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        assert_eq!(interp.interpret(&stmts), Err(RuntimeError::Unlowered));
+        assert_eq!(interp.interpret(&prepare(stmts)), Ok(()));
+        assert_eq!(global(&interp, "n"), Value::Number(3.));
+    }
+
+    #[test]
+    fn test_evaluate_statement() {
+        let mut interp = Interpreter::new();
+        let ast = Parser::new(Scanner::new("1 + 2;")).declaration().unwrap();
+        interp.interrupt_handle().interrupt();
+        assert_eq!(
+            interp.evaluate_statement(&ast),
+            Err(RuntimeError::Interrupted)
+        );
+        assert_eq!(interp.evaluate_statement(&ast), Ok(Some(Value::Number(3.))));
+        let ast = Parser::new(Scanner::new("var a;")).declaration().unwrap();
+        assert_eq!(interp.evaluate_statement(&ast), Ok(None));
+    }
+
+    #[test]
+    fn test_interrupt_running_loop() {
+        let mut interp = Interpreter::new();
+        let ast = Parser::new(Scanner::new("while (true) {}"))
+            .declaration()
+            .unwrap();
+        let handle = interp.interrupt_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            handle.interrupt();
+        });
+        assert_eq!(interp.statement(&ast), Err(RuntimeError::Interrupted));
+        canceller.join().unwrap();
+    }
 }
@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A cloneable handle that can cancel a running `Interpreter` from another
+/// thread or a signal handler.
+///
+/// The interpreter polls the flag between statements and on every loop
+/// iteration, so cancellation is cooperative: the current statement finishes
+/// its expression before `RuntimeError::Interrupted` is returned.  The flag
+/// guards no other data, so `Relaxed` ordering is enough.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request that the running script stop as soon as possible.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Clear a pending interrupt, eg one that fired while nothing was running.
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
@@ -1,11 +1,22 @@
+mod coverage;
 mod environment;
 mod error;
+mod hook;
+#[allow(clippy::module_inception)]
 mod interpreter;
+mod interrupt;
+mod module;
+mod profile;
 mod value;
 
+pub use coverage::{Branch, Coverage};
 pub use environment::Environment;
 pub use error::RuntimeError;
+pub use hook::Hook;
 pub use interpreter::Interpreter;
-pub use value::Value;
+pub use interrupt::InterruptHandle;
+pub use module::{FileLoader, MemoryLoader, Module, ModuleLoader};
+pub use profile::{Profile, Stats};
+pub use value::{ErrorValue, Value};
 
 type RuntimeResult<T> = Result<T, RuntimeError>;
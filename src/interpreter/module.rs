@@ -0,0 +1,184 @@
+use super::Value;
+use crate::parse::SymbolMap;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The globals of an imported module, as bound by `import`.
+#[derive(Debug)]
+pub struct Module {
+    /// The path it was first imported by.
+    pub path: String,
+    pub globals: SymbolMap<Value>,
+}
+
+/// Modules are only equal to themselves, and each is only run once.
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// Finds and reads the modules a program imports.
+pub trait ModuleLoader {
+    /// The canonical name of the module `path` refers to when imported by
+    /// the module named `importer`, or by the main program if that is
+    /// `None`.  Imports with the same canonical name share one module.
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String>;
+
+    /// The source of the module with the canonical name `name`.
+    fn load(&self, name: &str) -> io::Result<String>;
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "module not found")
+}
+
+/// Loads modules from files, named by their canonical paths.  An import is
+/// looked for next to the module that imports it, then in each directory of
+/// the search path in turn.
+pub struct FileLoader {
+    search_path: Vec<PathBuf>,
+}
+
+impl FileLoader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self { search_path }
+    }
+
+    /// The loader for running `script`: imports are looked for in its
+    /// directory (or the current one if there is no script), and then in
+    /// each directory of `LOX_PATH`.
+    pub fn for_script(script: Option<&Path>) -> Self {
+        let script_dir = match script.and_then(Path::parent) {
+            Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let lox_path = std::env::var_os("LOX_PATH");
+        let search_path = std::iter::once(script_dir)
+            .chain(lox_path.iter().flat_map(std::env::split_paths))
+            .collect();
+        Self::new(search_path)
+    }
+}
+
+/// Searches the current directory.
+impl Default for FileLoader {
+    fn default() -> Self {
+        Self::new(vec![PathBuf::from(".")])
+    }
+}
+
+impl ModuleLoader for FileLoader {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String> {
+        let importer_dir = importer.and_then(|importer| Path::new(importer).parent());
+        let found = importer_dir
+            .into_iter()
+            .chain(self.search_path.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(not_found)?;
+        Ok(found.canonicalize()?.to_string_lossy().into_owned())
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        std::fs::read_to_string(name)
+    }
+}
+
+/// Serves modules from memory, for hosts that embed their own sources.
+/// Names are `/`-separated paths from the root, and an import is looked for
+/// next to the module that imports it and then from the root.
+#[derive(Default)]
+pub struct MemoryLoader {
+    sources: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a module, or replace the one that had this name.
+    pub fn insert(&mut self, name: &str, source: impl Into<String>) {
+        self.sources.insert(normalize("", name), source.into());
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String> {
+        let importer_dir = importer
+            .and_then(|importer| importer.rsplit_once('/'))
+            .map_or("", |(dir, _)| dir);
+        [normalize(importer_dir, path), normalize("", path)]
+            .into_iter()
+            .find(|name| self.sources.contains_key(name))
+            .ok_or_else(not_found)
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        self.sources.get(name).cloned().ok_or_else(not_found)
+    }
+}
+
+/// `path` relative to the directory `dir`, without any `.` or `..` parts.
+fn normalize(dir: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in dir.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_resolve() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("lib.lox", "");
+        loader.insert("./util/lib.lox", "");
+        loader.insert("util/strings.lox", "");
+        let resolve = |path, importer| loader.resolve(path, importer).ok();
+        assert_eq!(resolve("lib.lox", None), Some("lib.lox".to_owned()));
+        assert_eq!(
+            resolve("util/lib.lox", None),
+            Some("util/lib.lox".to_owned())
+        );
+        // Next to the importer first, then from the root.
+        let importer = Some("util/strings.lox");
+        assert_eq!(
+            resolve("lib.lox", importer),
+            Some("util/lib.lox".to_owned())
+        );
+        assert_eq!(resolve("../lib.lox", importer), Some("lib.lox".to_owned()));
+        assert_eq!(resolve("strings.lox", None), None);
+        assert_eq!(
+            resolve("strings.lox", importer),
+            Some("util/strings.lox".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_file_resolve() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let loader = FileLoader::new(vec![root.join("lox")]);
+        let canonical = |path: PathBuf| path.canonicalize().unwrap().to_string_lossy().into_owned();
+        let lib = canonical(root.join("lox/modules/lib.lox"));
+        assert_eq!(loader.resolve("modules/lib.lox", None).unwrap(), lib);
+        let importer = root.join("lox/modules/nested/helper.lox");
+        let importer = importer.to_str();
+        assert_eq!(loader.resolve("../lib.lox", importer).unwrap(), lib);
+        assert_eq!(
+            loader.resolve("lib.lox", None).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
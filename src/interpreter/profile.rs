@@ -0,0 +1,278 @@
+use crate::parse::{Expr, Stmt, StmtKind};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// How often something ran and how long it took, including everything it
+/// ran in turn.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub count: u64,
+    pub time: Duration,
+}
+
+/// The results of profiling a program.
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// Statements by the line they start on, slowest first.
+    pub lines: Vec<(usize, Stats)>,
+    /// Statement and expression kinds, slowest first.
+    pub constructs: Vec<(&'static str, Stats)>,
+    // Time spent in each stack of statements, excluding nested statements.
+    stacks: Vec<(String, Duration)>,
+}
+
+impl Profile {
+    /// A table of lines and constructs, slowest first.
+    pub fn report(&self) -> String {
+        let mut report = format!("{:>6} {:>10} {:>12}\n", "line", "count", "time");
+        for (line, stats) in &self.lines {
+            let _ = writeln!(
+                report,
+                "{line:>6} {:>10} {:>12}",
+                stats.count,
+                millis(stats.time)
+            );
+        }
+        let _ = write!(
+            report,
+            "\n{:<12} {:>10} {:>12}\n",
+            "construct", "count", "time"
+        );
+        for (construct, stats) in &self.constructs {
+            let _ = writeln!(
+                report,
+                "{construct:<12} {:>10} {:>12}",
+                stats.count,
+                millis(stats.time)
+            );
+        }
+        report
+    }
+
+    /// Stacks of statements in the folded format read by flamegraph tools:
+    /// semicolon-separated frames, then the time in nanoseconds.
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, time)| format!("{stack} {}\n", time.as_nanos()))
+            .collect()
+    }
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.)
+}
+
+struct Frame {
+    construct: &'static str,
+    // Statement line, or None for expressions.
+    line: Option<usize>,
+    start: Instant,
+    // Time spent in statements nested directly inside this one.
+    nested: Duration,
+}
+
+/// Records statements and expressions as the interpreter runs them.
+#[derive(Default)]
+pub struct Profiler {
+    stack: Vec<Frame>,
+    lines: HashMap<usize, Stats>,
+    constructs: HashMap<&'static str, Stats>,
+    stacks: HashMap<String, Duration>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enter_statement(&mut self, stmt: &Stmt) {
+        self.enter(statement_name(&stmt.kind), Some(stmt.line));
+    }
+
+    pub fn enter_expression(&mut self, expr: &Expr) {
+        self.enter(expression_name(expr), None);
+    }
+
+    fn enter(&mut self, construct: &'static str, line: Option<usize>) {
+        self.stack.push(Frame {
+            construct,
+            line,
+            start: Instant::now(),
+            nested: Duration::ZERO,
+        });
+    }
+
+    /// Finish the innermost statement or expression.
+    pub fn exit(&mut self) {
+        let frame = self.stack.pop().expect("Profiler exit without enter");
+        let elapsed = frame.start.elapsed();
+
+        // Recursive constructs and lines only count their outermost time, so
+        // that totals aren't counted twice.
+        let stats = self.constructs.entry(frame.construct).or_default();
+        stats.count += 1;
+        if !self.stack.iter().any(|f| f.construct == frame.construct) {
+            stats.time += elapsed;
+        }
+
+        let Some(line) = frame.line else {
+            return;
+        };
+        let stats = self.lines.entry(line).or_default();
+        stats.count += 1;
+        if !self.stack.iter().any(|f| f.line == Some(line)) {
+            stats.time += elapsed;
+        }
+
+        let mut stack: Vec<String> = self
+            .stack
+            .iter()
+            .filter_map(|f| f.line.map(|line| format!("{}:{line}", f.construct)))
+            .collect();
+        stack.push(format!("{}:{line}", frame.construct));
+        *self.stacks.entry(stack.join(";")).or_default() += elapsed.saturating_sub(frame.nested);
+        if let Some(parent) = self.stack.iter_mut().rev().find(|f| f.line.is_some()) {
+            parent.nested += elapsed;
+        }
+    }
+
+    pub fn finish(self) -> Profile {
+        let mut lines: Vec<(usize, Stats)> = self.lines.into_iter().collect();
+        lines.sort_by(|(a_line, a), (b_line, b)| b.time.cmp(&a.time).then(a_line.cmp(b_line)));
+        let mut constructs: Vec<(&'static str, Stats)> = self.constructs.into_iter().collect();
+        constructs.sort_by(|(a_name, a), (b_name, b)| b.time.cmp(&a.time).then(a_name.cmp(b_name)));
+        let mut stacks: Vec<(String, Duration)> = self.stacks.into_iter().collect();
+        stacks.sort();
+        Profile {
+            lines,
+            constructs,
+            stacks,
+        }
+    }
+}
+
+fn statement_name(kind: &StmtKind) -> &'static str {
+    match kind {
+        StmtKind::Expression(_) => "expression",
+        StmtKind::If { .. } => "if",
+        StmtKind::Print(_) => "print",
+        StmtKind::Var { .. } => "var",
+        StmtKind::Block(_) => "block",
+        StmtKind::Import { .. } => "import",
+        StmtKind::While { .. } => "while",
+        StmtKind::For { .. } => "for",
+        StmtKind::Throw(_) => "throw",
+        StmtKind::Try { .. } => "try",
+    }
+}
+
+fn expression_name(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Unary { .. } => "unary",
+        Expr::Binary { .. } => "binary",
+        Expr::Grouping(_) => "grouping",
+        Expr::Interpolation(_) => "interpolation",
+        Expr::Literal(_) => "literal",
+        Expr::Variable { .. } => "variable",
+        Expr::Get { .. } => "get",
+        Expr::Assign { .. } => "assign",
+        Expr::Update { .. } => "update",
+        Expr::Logical { .. } => "logical",
+        Expr::Conditional { .. } => "conditional",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, MemoryLoader};
+    use crate::lex::Scanner;
+    use crate::parse::{lower::lower, Parser};
+
+    fn profile(source: &str) -> Profile {
+        let stmts = lower(Parser::new(Scanner::new(source)).parse().unwrap());
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.enable_profiling();
+        interp.interpret(&stmts).unwrap();
+        interp.take_profile().unwrap()
+    }
+
+    fn count<T: PartialEq>(stats: &[(T, Stats)], key: T) -> u64 {
+        stats
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map_or(0, |(_, s)| s.count)
+    }
+
+    const SOURCE: &str = "var i = 0;
+while (i < 3) {
+  i = i + 1;
+}
+print i;";
+
+    #[test]
+    fn test_counts() {
+        let profile = profile(SOURCE);
+        assert_eq!(count(&profile.lines, 1), 1);
+        assert_eq!(count(&profile.lines, 2), 4);
+        assert_eq!(count(&profile.lines, 3), 3);
+        assert_eq!(count(&profile.lines, 5), 1);
+        assert_eq!(count(&profile.constructs, "while"), 1);
+        assert_eq!(count(&profile.constructs, "assign"), 3);
+        // Three additions and four comparisons.
+        assert_eq!(count(&profile.constructs, "binary"), 7);
+        assert_eq!(count(&profile.constructs, "variable"), 8);
+    }
+
+    #[test]
+    fn test_modules_not_profiled() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("lib.lox", "var a = 1;\nvar b = 2;\nvar c = 3;");
+        let stmts = lower(
+            Parser::new(Scanner::new("import \"lib.lox\";"))
+                .parse()
+                .unwrap(),
+        );
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.set_module_loader(Box::new(loader));
+        interp.enable_profiling();
+        interp.interpret(&stmts).unwrap();
+        let profile = interp.take_profile().unwrap();
+        let lines: Vec<usize> = profile.lines.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1]);
+        assert_eq!(count(&profile.constructs, "var"), 0);
+    }
+
+    #[test]
+    fn test_folded() {
+        let folded = profile(SOURCE).folded();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            vec![
+                "print:5",
+                "var:1",
+                "while:2",
+                "while:2;block:2",
+                "while:2;block:2;expression:3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let report = profile(SOURCE).report();
+        let mut sections = report.split("\n\n");
+        let lines = sections.next().unwrap();
+        assert!(lines.starts_with("  line      count"));
+        assert_eq!(lines.lines().count(), 5);
+        let constructs = sections.next().unwrap();
+        assert!(constructs.starts_with("construct"));
+        assert!(constructs.contains("\nwhile "));
+    }
+}
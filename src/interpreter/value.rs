@@ -1,13 +1,25 @@
 use std::fmt::{Display, Error as FmtError, Formatter};
 
+use super::Module;
 use crate::parse::Literal;
+use std::rc::Rc;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Nil,
     Bool(bool),
     Number(f64),
-    String(String),
+    String(Rc<str>),
+    /// A runtime error caught by `catch`.
+    Error(Rc<ErrorValue>),
+    Module(Rc<Module>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ErrorValue {
+    pub message: Rc<str>,
+    /// The line of the statement that failed.
+    pub line: usize,
 }
 
 impl Value {
@@ -16,7 +28,7 @@ impl Value {
             Literal::Nil => Value::Nil,
             Literal::Bool(b) => Value::Bool(*b),
             Literal::Number(num) => Value::Number(*num),
-            Literal::String(s) => Value::String(s.to_owned()),
+            Literal::String(s) => Value::String(Rc::clone(s)),
         }
     }
 
@@ -27,15 +39,111 @@ impl Value {
             _ => true,
         }
     }
+
+    /// The developer-facing form of this value, as shown by the REPL and in
+    /// error messages.  Unlike `Display`, strings are quoted and escaped.
+    pub fn repr(&self) -> Repr<'_> {
+        Repr(self)
+    }
 }
 
+/// User-facing formatting, as used by `print`.
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match self {
             Self::Nil => write!(f, "nil"),
             Self::Bool(b) => write!(f, "{b}"),
-            Self::Number(num) => write!(f, "{num}"),
-            Self::String(s) => write!(f, "\"{s}\""),
+            Self::Number(num) => fmt_number(*num, f),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Error(err) => write!(f, "{}", err.message),
+            Self::Module(module) => write!(f, "<module {:?}>", module.path),
         }
     }
 }
+
+/// Wrapper returned by `Value::repr`.
+pub struct Repr<'a>(&'a Value);
+
+impl Display for Repr<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self.0 {
+            Value::String(s) => write!(f, "{s:?}"),
+            Value::Error(err) => write!(f, "<error: {}>", err.message),
+            value => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Format a number in the shortest form that reads back as the same value.
+/// Integral values have no trailing `.0` and `-0` keeps its sign.  Magnitudes
+/// of at least 1e21 or below 1e-7 use an exponent, eg `1e21` or `1.5e-8`.
+/// Non-finite values are `nan`, `inf` and `-inf`, as clox prints them.
+/// Everything that shows a number to the user goes through here.
+fn fmt_number(num: f64, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+    if num.is_nan() {
+        write!(f, "nan")
+    } else if num.is_infinite() {
+        write!(f, "{}", if num > 0. { "inf" } else { "-inf" })
+    } else if num != 0. && (num.abs() >= 1e21 || num.abs() < 1e-7) {
+        write!(f, "{num:e}")
+    } else {
+        write!(f, "{num}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorValue, Value};
+
+    fn assert_display(value: Value, display: &str, repr: &str) {
+        assert_eq!(value.to_string(), display);
+        assert_eq!(value.repr().to_string(), repr);
+    }
+
+    #[test]
+    fn test_format_simple() {
+        assert_display(Value::Nil, "nil", "nil");
+        assert_display(Value::Bool(true), "true", "true");
+        assert_display(Value::Bool(false), "false", "false");
+    }
+
+    #[test]
+    fn test_format_string() {
+        assert_display(Value::String("hello".into()), "hello", r#""hello""#);
+        assert_display(
+            Value::String("say \"hi\"\n".into()),
+            "say \"hi\"\n",
+            r#""say \"hi\"\n""#,
+        );
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_display(Value::Number(1.), "1", "1");
+        assert_display(Value::Number(-12.5), "-12.5", "-12.5");
+        assert_display(
+            Value::Number(0.1 + 0.2),
+            "0.30000000000000004",
+            "0.30000000000000004",
+        );
+        assert_display(Value::Number(0.), "0", "0");
+        assert_display(Value::Number(-0.), "-0", "-0");
+        assert_display(Value::Number(123456789.), "123456789", "123456789");
+        assert_display(Value::Number(1e21), "1e21", "1e21");
+        assert_display(Value::Number(-2.5e300), "-2.5e300", "-2.5e300");
+        assert_display(Value::Number(1.5e-8), "1.5e-8", "1.5e-8");
+        assert_display(Value::Number(f64::NAN), "nan", "nan");
+        assert_display(Value::Number(-f64::NAN), "nan", "nan");
+        assert_display(Value::Number(f64::INFINITY), "inf", "inf");
+        assert_display(Value::Number(f64::NEG_INFINITY), "-inf", "-inf");
+    }
+
+    #[test]
+    fn test_format_error() {
+        let err = ErrorValue {
+            message: "Oops.".into(),
+            line: 3,
+        };
+        assert_display(Value::Error(err.into()), "Oops.", "<error: Oops.>");
+    }
+}
@@ -1,4 +1,5 @@
 use super::{Token, TokenType};
+use std::borrow::Cow;
 use TokenType::*;
 
 pub struct Scanner<'a> {
@@ -7,6 +8,13 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
+    // Characters read so far on the current line, for computing columns.
+    line_chars: usize,
+    // Column of the token being scanned.
+    column: usize,
+    // For each `${` in a string that is still open, how many braces are open
+    // inside it.
+    interpolations: Vec<usize>,
     eof: bool,
 }
 
@@ -18,6 +26,9 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            line_chars: 0,
+            column: 1,
+            interpolations: Vec::new(),
             eof: false,
         }
     }
@@ -25,19 +36,75 @@ impl<'a> Scanner<'a> {
     fn scan_token(&mut self) -> Option<Token<'a>> {
         self.skip_whitespace();
         self.start = self.current;
+        self.column = self.current_column();
 
         let ch = self.advance()?;
         let typ = match ch {
             '(' => LeftParen,
             ')' => RightParen,
-            '{' => LeftBrace,
-            '}' => RightBrace,
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                LeftBrace
+            }
+            '}' => match self.interpolations.last_mut() {
+                // The end of an interpolated expression, so the string goes on.
+                Some(0) => {
+                    self.interpolations.pop();
+                    return Some(self.string());
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    RightBrace
+                }
+                None => RightBrace,
+            },
             ',' => Comma,
             '.' => Dot,
-            '-' => Minus,
-            '+' => Plus,
+            '-' => {
+                if self.match_next('=') {
+                    MinusEqual
+                } else if self.match_next('-') {
+                    MinusMinus
+                } else {
+                    Minus
+                }
+            }
+            '+' => {
+                if self.match_next('=') {
+                    PlusEqual
+                } else if self.match_next('+') {
+                    PlusPlus
+                } else {
+                    Plus
+                }
+            }
             ';' => Semicolon,
-            '*' => Star,
+            '?' => Question,
+            ':' => Colon,
+            '%' => Percent,
+            '&' => Ampersand,
+            '|' => Pipe,
+            '^' => Caret,
+
+            '*' => {
+                if self.match_next('*') {
+                    StarStar
+                } else if self.match_next('=') {
+                    StarEqual
+                } else {
+                    Star
+                }
+            }
+            // `//` is a comment, so floor division is spelled `~/`.
+            '~' => {
+                if self.match_next('/') {
+                    TildeSlash
+                } else {
+                    Tilde
+                }
+            }
 
             '!' => {
                 if self.match_next('=') {
@@ -56,6 +123,8 @@ impl<'a> Scanner<'a> {
             '<' => {
                 if self.match_next('=') {
                     LessEqual
+                } else if self.match_next('<') {
+                    LessLess
                 } else {
                     Less
                 }
@@ -63,60 +132,56 @@ impl<'a> Scanner<'a> {
             '>' => {
                 if self.match_next('=') {
                     GreaterEqual
+                } else if self.match_next('>') {
+                    GreaterGreater
                 } else {
                     Greater
                 }
             }
             '/' => {
                 if self.match_next('/') {
+                    // `///` starts a doc comment, but `////` doesn't.
+                    let doc = self.match_next('/') && self.peek() != Some('/');
                     self.advance_while(|ch| ch != '\n');
-                    Comment
+                    if doc {
+                        DocComment
+                    } else {
+                        Comment
+                    }
+                } else if self.match_next('*') {
+                    return Some(self.block_comment());
+                } else if self.match_next('=') {
+                    SlashEqual
                 } else {
                     Slash
                 }
             }
 
-            // String literals, because they can embed newlines and have to be
-            // stripped of their surrounding double-quotes, need special handling.
-            '"' => {
-                // Skip initial '"'
-                self.start = self.current;
-                let starting_line = self.line;
-                self.advance_while(|ch| ch != '"');
-                let mut token = Token::new(starting_line, String_, self.current_lexeme());
-                // Skip final '"', but check to make sure everything's ok.
-                match self.advance() {
-                    // The expected case
-                    Some('"') => (),
-                    // This is a bug in our parser, because advance_while should ensure this never happens,
-                    Some(_) => {
-                        let current_line = self.line;
-                        panic!(
-                            r#"String literal parsing finished but next character is not a '"'.
-                        Started on line {starting_line}, error at line {current_line}.
-                        "#
-                        );
-                    }
-                    // Eof before string literal closed.
-                    None => {
-                        token = Token::new(self.line, ErrorUnclosedString, self.current_lexeme());
-                    }
-                }
-                return Some(token);
+            '"' if self.source[self.current..].starts_with("\"\"") => {
+                return Some(self.raw_string())
             }
+            '"' => return Some(self.string()),
 
-            '0'..='9' => self.advance_number(),
+            '0'..='9' => self.advance_number(ch),
 
-            'A'..='Z' | 'a'..='z' | '_' => {
-                self.advance_while(is_kw_char);
-                TokenType::get(self.current_lexeme())
+            ch if is_ident_start(ch) => {
+                self.advance_while(is_ident_char);
+                let lexeme = self.current_lexeme();
+                return Some(match TokenType::get(lexeme) {
+                    Identifier => Token::identifier(self.line, self.column, lexeme),
+                    keyword => Token::new(self.line, self.column, keyword, lexeme),
+                });
             }
 
             _ => ErrorUnknownToken,
         };
 
         let lexeme = self.current_lexeme();
-        Some(Token::new(self.line, typ, lexeme))
+        Some(Token::new(self.line, self.column, typ, lexeme))
+    }
+
+    fn current_column(&self) -> usize {
+        self.line_chars + 1
     }
 
     fn skip_whitespace(&mut self) {
@@ -128,30 +193,202 @@ impl<'a> Scanner<'a> {
         &self.source[self.start..self.current]
     }
 
-    // Advance past the rest of the number. It assumes the first digit has already been consumed.
-    fn advance_number(&mut self) -> TokenType {
-        let pred = |ch| '0' <= ch && ch <= '9';
-        self.advance_while(pred);
+    /// Scan the rest of a `/* */` comment after its opening `/*`.  Block
+    /// comments nest, so `/* a /* b */ c */` is one comment.
+    fn block_comment(&mut self) -> Token<'a> {
+        let line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.match_next('*') => depth += 1,
+                Some('*') if self.match_next('/') => depth -= 1,
+                Some(_) => (),
+                None => {
+                    return Token::new(
+                        self.line,
+                        self.column,
+                        ErrorUnterminatedComment,
+                        self.current_lexeme(),
+                    )
+                }
+            }
+        }
+        Token::new(line, self.column, Comment, self.current_lexeme())
+    }
 
-        // If there's a period, keep matching digits for a float.
-        // If there are no digits, that's a lex error.
-        if self.match_next('.') {
-            if self.advance_if(pred).is_some() {
-                self.advance_while(pred);
+    /// Scan the rest of a string literal, after its opening quote or the `}`
+    /// ending an interpolated expression.
+    fn string(&mut self) -> Token<'a> {
+        let line = self.line;
+        let mut value = Cow::Borrowed("");
+        let mut chunk_start = self.current;
+        // The first invalid escape, reported once the string is closed.
+        let mut invalid = None;
+        let typ = loop {
+            let escape_start = self.current;
+            let escape_column = self.current_column();
+            match self.advance() {
+                Some('"') => break String_,
+                Some('$') if self.match_next('{') => {
+                    self.interpolations.push(0);
+                    break Interpolation;
+                }
+                Some('\\') => {
+                    value += &self.source[chunk_start..escape_start];
+                    match self.escape() {
+                        Some(ch) => value.to_mut().push(ch),
+                        None if invalid.is_none() => {
+                            let lexeme = &self.source[escape_start..self.current];
+                            invalid = Some(Token::new(
+                                self.line,
+                                escape_column,
+                                ErrorInvalidEscape,
+                                lexeme,
+                            ));
+                        }
+                        None => (),
+                    }
+                    chunk_start = self.current;
+                }
+                Some(_) => (),
+                None => return self.unclosed_string(),
+            }
+        };
+        if let Some(token) = invalid {
+            return token;
+        }
+        let terminator = if typ == Interpolation { "${" } else { "\"" };
+        value += &self.source[chunk_start..self.current - terminator.len()];
+        Token {
+            typ,
+            ..Token::string(line, self.column, self.current_lexeme(), value)
+        }
+    }
+
+    /// Advance past an escape sequence after its backslash, returning the
+    /// character it stands for, or `None` if it's invalid.
+    fn escape(&mut self) -> Option<char> {
+        match self.advance_if(|_| true)? {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            'u' => {
+                if !self.match_next('{') {
+                    return None;
+                }
+                let start = self.current;
+                while self.current - start < 6
+                    && self.advance_if(|ch| ch.is_ascii_hexdigit()).is_some()
+                {}
+                let digits = &self.source[start..self.current];
+                if digits.is_empty() || !self.match_next('}') {
+                    return None;
+                }
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+
+    /// Scan the rest of a `"""`-quoted string after its first quote.  Its
+    /// contents are taken exactly as written, newlines and backslashes
+    /// included.
+    fn raw_string(&mut self) -> Token<'a> {
+        let line = self.line;
+        self.advance();
+        self.advance();
+        let start = self.current;
+        let Some(len) = self.source[start..].find("\"\"\"") else {
+            self.advance_while(|_| true);
+            return self.unclosed_string();
+        };
+        while self.current < start + len {
+            self.advance();
+        }
+        let value = Cow::Borrowed(&self.source[start..self.current]);
+        for _ in 0..3 {
+            self.advance();
+        }
+        Token::string(line, self.column, self.current_lexeme(), value)
+    }
+
+    /// The error for a string still open at the end of the source.
+    fn unclosed_string(&self) -> Token<'a> {
+        Token::new(
+            self.line,
+            self.column,
+            ErrorUnclosedString,
+            self.current_lexeme(),
+        )
+    }
+
+    // Advance past the rest of the number, whose first digit is `first`.
+    //
+    // Numbers are decimal with an optional fraction and exponent, or
+    // hexadecimal (`0x`) or binary (`0b`) integers.  Any of them can separate
+    // digits with `_`.
+    fn advance_number(&mut self, first: char) -> TokenType {
+        let radix = match (first, self.peek()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+        let valid = if radix == 10 {
+            // Each part is scanned even if an earlier one was malformed, so
+            // that the whole number ends up in the error token.
+            let integer = self.advance_digits(10, true);
+            let fraction = !self.match_next('.') || self.advance_digits(10, false);
+            let exponent = self.advance_if(|ch| ch == 'e' || ch == 'E').is_none() || {
+                self.advance_if(|ch| ch == '+' || ch == '-');
+                self.advance_digits(10, false)
+            };
+            integer && fraction && exponent
+        } else {
+            self.advance();
+            self.advance_digits(radix, false)
+        };
+        // Letters or digits stuck to the end, eg `0b12` or `3rd`, are part
+        // of the same mistake.
+        let trailing = self.advance_if(is_ident_char).is_some();
+        self.advance_while(is_ident_char);
+        if valid && !trailing {
+            TokenType::Number
+        } else {
+            TokenType::ErrorMalformedNumber
+        }
+    }
+
+    /// Advance past digits in `radix` and `_` separators, returning whether
+    /// there was at least one digit and every separator was between two.
+    fn advance_digits(&mut self, radix: u32, mut after_digit: bool) -> bool {
+        let mut any_digits = after_digit;
+        let mut valid = true;
+        while let Some(ch) = self.advance_if(|ch| ch.is_digit(radix) || ch == '_') {
+            if ch == '_' {
+                valid &= after_digit;
+                after_digit = false;
             } else {
-                return TokenType::ErrorMalformedNumber;
+                any_digits = true;
+                after_digit = true;
             }
         }
-        TokenType::Number
+        valid && any_digits && after_digit
     }
 
     /// Advance to the next char, if any
     fn advance(&mut self) -> Option<char> {
         let (_idx, ch) = self.char_idxs.next()?;
+        self.current = self.next_idx();
         if ch == '\n' {
             self.line += 1;
+            self.line_chars = 0;
+        } else {
+            self.line_chars += 1;
         }
-        self.current = self.next_idx();
         Some(ch)
     }
 
@@ -195,23 +432,25 @@ impl<'a> Iterator for Scanner<'a> {
         } else {
             self.scan_token().or_else(|| {
                 self.eof = true;
-                Some(Token {
-                    line: self.line,
-                    typ: Eof,
-                    lexeme: "",
-                })
+                Some(Token::new(self.line, self.current_column(), Eof, ""))
             })
         }
     }
 }
 
-fn is_kw_char(ch: char) -> bool {
-    ('A' <= ch && ch <= 'Z') || ('a' <= ch && ch <= 'z') || ('0' <= ch && ch <= '9') || ch == '_'
+/// Identifiers follow Unicode's XID rules, plus `_` anywhere.
+fn is_ident_start(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+fn is_ident_char(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
 }
 
 #[cfg(test)]
 mod tests {
     use super::{Scanner, Token, TokenType};
+    use std::borrow::Cow;
 
     fn assert_scan(source: &str, expected: Vec<Token>) {
         let tokens: Vec<Token> = Scanner::new(source).collect();
@@ -220,17 +459,17 @@ mod tests {
 
     #[test]
     fn test_empty_scan() {
-        assert_scan("", vec![Token::new(1, TokenType::Eof, "")]);
+        assert_scan("", vec![Token::new(1, 1, TokenType::Eof, "")]);
     }
 
     #[test]
     fn test_empty_whitespace_scan() {
-        assert_scan("  \t ", vec![Token::new(1, TokenType::Eof, "")]);
+        assert_scan("  \t ", vec![Token::new(1, 5, TokenType::Eof, "")]);
     }
 
     #[test]
     fn test_empty_whitespace_with_newline_scan() {
-        assert_scan("  \n\r  ", vec![Token::new(2, TokenType::Eof, "")]);
+        assert_scan("  \n\r  ", vec![Token::new(2, 4, TokenType::Eof, "")]);
     }
 
     #[test]
@@ -241,43 +480,163 @@ mod tests {
         }
         "#,
             vec![
-                Token::new(1, TokenType::Fun, "fun"),
-                Token::new(1, TokenType::Identifier, "printSum"),
-                Token::new(1, TokenType::LeftParen, "("),
-                Token::new(1, TokenType::Identifier, "a"),
-                Token::new(1, TokenType::Comma, ","),
-                Token::new(1, TokenType::Identifier, "b"),
-                Token::new(1, TokenType::RightParen, ")"),
-                Token::new(1, TokenType::LeftBrace, "{"),
-                Token::new(2, TokenType::Print, "print"),
-                Token::new(2, TokenType::Identifier, "a"),
-                Token::new(2, TokenType::Plus, "+"),
-                Token::new(2, TokenType::Identifier, "b"),
-                Token::new(2, TokenType::Semicolon, ";"),
-                Token::new(3, TokenType::RightBrace, "}"),
-                Token::new(4, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::Fun, "fun"),
+                Token::new(1, 5, TokenType::Identifier, "printSum"),
+                Token::new(1, 13, TokenType::LeftParen, "("),
+                Token::new(1, 14, TokenType::Identifier, "a"),
+                Token::new(1, 15, TokenType::Comma, ","),
+                Token::new(1, 17, TokenType::Identifier, "b"),
+                Token::new(1, 18, TokenType::RightParen, ")"),
+                Token::new(1, 20, TokenType::LeftBrace, "{"),
+                Token::new(2, 13, TokenType::Print, "print"),
+                Token::new(2, 19, TokenType::Identifier, "a"),
+                Token::new(2, 21, TokenType::Plus, "+"),
+                Token::new(2, 23, TokenType::Identifier, "b"),
+                Token::new(2, 24, TokenType::Semicolon, ";"),
+                Token::new(3, 9, TokenType::RightBrace, "}"),
+                Token::new(4, 9, TokenType::Eof, ""),
             ],
         );
     }
 
+    #[test]
+    fn test_operator_scan() {
+        let types: Vec<TokenType> = Scanner::new("% ~/ ~ ** * & | ^ << <= >> >= += ++ -= -- *= /=")
+            .map(|t| t.typ)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Percent,
+                TokenType::TildeSlash,
+                TokenType::Tilde,
+                TokenType::StarStar,
+                TokenType::Star,
+                TokenType::Ampersand,
+                TokenType::Pipe,
+                TokenType::Caret,
+                TokenType::LessLess,
+                TokenType::LessEqual,
+                TokenType::GreaterGreater,
+                TokenType::GreaterEqual,
+                TokenType::PlusEqual,
+                TokenType::PlusPlus,
+                TokenType::MinusEqual,
+                TokenType::MinusMinus,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_string_literal_scan() {
         assert_scan(
             r#""this is a fun 'literal'""#,
             vec![
-                Token::new(1, TokenType::String_, "this is a fun 'literal'"),
-                Token::new(1, TokenType::Eof, ""),
+                Token::string(
+                    1,
+                    1,
+                    r#""this is a fun 'literal'""#,
+                    Cow::Borrowed("this is a fun 'literal'"),
+                ),
+                Token::new(1, 26, TokenType::Eof, ""),
+            ],
+        );
+    }
+
+    fn string_value(source: &str) -> Option<String> {
+        let token = Scanner::new(source).next().unwrap();
+        assert_eq!(token.typ, TokenType::String_, "{token:?}");
+        assert_eq!(token.lexeme, source);
+        token.value.map(Cow::into_owned)
+    }
+
+    #[test]
+    fn test_string_escapes_scan() {
+        assert_eq!(
+            string_value(r#""a\tb\nc\\d\"e""#).as_deref(),
+            Some("a\tb\nc\\d\"e")
+        );
+        assert_eq!(
+            string_value(r#""\u{48}\u{e9}\u{1F600}!""#).as_deref(),
+            Some("Hé😀!")
+        );
+        assert_eq!(string_value(r#""""#).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_string_invalid_escape_scan() {
+        assert_scan(
+            r#"x = "ok \q \z" + 1"#,
+            vec![
+                Token::new(1, 1, TokenType::Identifier, "x"),
+                Token::new(1, 3, TokenType::Equal, "="),
+                Token::new(1, 9, TokenType::ErrorInvalidEscape, r"\q"),
+                Token::new(1, 16, TokenType::Plus, "+"),
+                Token::new(1, 18, TokenType::Number, "1"),
+                Token::new(1, 19, TokenType::Eof, ""),
+            ],
+        );
+        for source in [
+            r#""\u{}""#,
+            r#""\u{110000}""#,
+            r#""\u{1234567}""#,
+            r#""\u41""#,
+        ] {
+            let token = Scanner::new(source).next().unwrap();
+            assert_eq!(token.typ, TokenType::ErrorInvalidEscape, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_interpolation_scan() {
+        let interpolation = |line, column, lexeme, value| Token {
+            typ: TokenType::Interpolation,
+            ..Token::string(line, column, lexeme, Cow::Borrowed(value))
+        };
+        assert_scan(
+            r#""a ${ {x} } b ${"${y}"}\${}""#,
+            vec![
+                interpolation(1, 1, "\"a ${", "a "),
+                Token::new(1, 7, TokenType::LeftBrace, "{"),
+                Token::new(1, 8, TokenType::Identifier, "x"),
+                Token::new(1, 9, TokenType::RightBrace, "}"),
+                interpolation(1, 11, "} b ${", " b "),
+                interpolation(1, 17, "\"${", ""),
+                Token::new(1, 20, TokenType::Identifier, "y"),
+                Token::string(1, 21, "}\"", Cow::Borrowed("")),
+                Token::string(1, 23, "}\\${}\"", Cow::Borrowed("${}")),
+                Token::new(1, 29, TokenType::Eof, ""),
             ],
         );
     }
 
+    #[test]
+    fn test_raw_string_scan() {
+        let source = "\"\"\"first \"line\"\n  \\n second\"\"\"";
+        assert_scan(
+            source,
+            vec![
+                Token::string(1, 1, source, Cow::Borrowed("first \"line\"\n  \\n second")),
+                Token::new(2, 15, TokenType::Eof, ""),
+            ],
+        );
+        assert_eq!(string_value(r#""""""""#).as_deref(), Some(""));
+        assert_eq!(
+            Scanner::new("\"\"\"open\"\"").next().unwrap().typ,
+            TokenType::ErrorUnclosedString
+        );
+    }
+
     #[test]
     fn test_string_literal_unclosed_scan() {
         assert_scan(
             "\"a literal\n more",
             vec![
-                Token::new(2, TokenType::ErrorUnclosedString, "a literal\n more"),
-                Token::new(2, TokenType::Eof, ""),
+                Token::new(2, 1, TokenType::ErrorUnclosedString, "\"a literal\n more"),
+                Token::new(2, 6, TokenType::Eof, ""),
             ],
         );
     }
@@ -287,12 +646,75 @@ mod tests {
         assert_scan(
             "1 // comment \n 2",
             vec![
-                Token::new(1, TokenType::Number, "1"),
-                Token::new(1, TokenType::Comment, "// comment "),
-                Token::new(2, TokenType::Number, "2"),
-                Token::new(2, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::Number, "1"),
+                Token::new(1, 3, TokenType::Comment, "// comment "),
+                Token::new(2, 2, TokenType::Number, "2"),
+                Token::new(2, 3, TokenType::Eof, ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_block_comment_scan() {
+        assert_scan(
+            "1 /* a /* nested\n */ comment */ 2",
+            vec![
+                Token::new(1, 1, TokenType::Number, "1"),
+                Token::new(1, 3, TokenType::Comment, "/* a /* nested\n */ comment */"),
+                Token::new(2, 16, TokenType::Number, "2"),
+                Token::new(2, 17, TokenType::Eof, ""),
+            ],
+        );
+        assert_scan(
+            "/* open /* */\n",
+            vec![
+                Token::new(2, 1, TokenType::ErrorUnterminatedComment, "/* open /* */\n"),
+                Token::new(2, 1, TokenType::Eof, ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_doc_comment_scan() {
+        assert_scan(
+            "/// doc\n//// rule",
+            vec![
+                Token::new(1, 1, TokenType::DocComment, "/// doc"),
+                Token::new(2, 1, TokenType::Comment, "//// rule"),
+                Token::new(2, 10, TokenType::Eof, ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifier_scan() {
+        assert_scan(
+            "var größe = \"日本\"; π_2 ¬",
+            vec![
+                Token::new(1, 1, TokenType::Var, "var"),
+                Token::new(1, 5, TokenType::Identifier, "größe"),
+                Token::new(1, 11, TokenType::Equal, "="),
+                Token::string(1, 13, "\"日本\"", Cow::Borrowed("日本")),
+                Token::new(1, 17, TokenType::Semicolon, ";"),
+                Token::new(1, 19, TokenType::Identifier, "π_2"),
+                Token::new(1, 23, TokenType::ErrorUnknownToken, "¬"),
+                Token::new(1, 24, TokenType::Eof, ""),
             ],
         );
+        // Combining characters can continue but not start an identifier.
+        let token = Scanner::new("\u{301}a").next().unwrap();
+        assert_eq!(token.typ, TokenType::ErrorUnknownToken);
+    }
+
+    #[test]
+    fn test_identifier_normalized_scan() {
+        // "é" as `e` plus a combining acute accent, then precomposed.
+        let decomposed = Scanner::new("e\u{301}").next().unwrap();
+        let composed = Scanner::new("\u{e9}").next().unwrap();
+        assert_eq!(decomposed.lexeme, "e\u{301}");
+        assert_eq!(decomposed.name(), "\u{e9}");
+        assert_eq!(composed.name(), "\u{e9}");
+        assert_eq!(composed.value, None);
     }
 
     #[test]
@@ -300,33 +722,74 @@ mod tests {
         assert_scan(
             "0123",
             vec![
-                Token::new(1, TokenType::Number, "0123"),
-                Token::new(1, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::Number, "0123"),
+                Token::new(1, 5, TokenType::Eof, ""),
             ],
         );
 
         assert_scan(
             "0123.456",
             vec![
-                Token::new(1, TokenType::Number, "0123.456"),
-                Token::new(1, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::Number, "0123.456"),
+                Token::new(1, 9, TokenType::Eof, ""),
             ],
         );
 
         assert_scan(
             "0.4",
             vec![
-                Token::new(1, TokenType::Number, "0.4"),
-                Token::new(1, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::Number, "0.4"),
+                Token::new(1, 4, TokenType::Eof, ""),
             ],
         );
 
         assert_scan(
             "12.",
             vec![
-                Token::new(1, TokenType::ErrorMalformedNumber, "12."),
-                Token::new(1, TokenType::Eof, ""),
+                Token::new(1, 1, TokenType::ErrorMalformedNumber, "12."),
+                Token::new(1, 4, TokenType::Eof, ""),
             ],
         );
     }
+
+    #[test]
+    fn test_number_forms_scan() {
+        for source in [
+            "1e6",
+            "2.5E-3",
+            "1e+10",
+            "0xFF",
+            "0XdeadBEEF",
+            "0b1010",
+            "0B1",
+            "1_000_000",
+            "0xFF_FF",
+            "0b1010_0101",
+            "1_0.2_5e1_0",
+        ] {
+            assert_scan(
+                source,
+                vec![
+                    Token::new(1, 1, TokenType::Number, source),
+                    Token::new(1, source.len() + 1, TokenType::Eof, ""),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_number_scan() {
+        for source in [
+            "1e", "1e+", "2.5e-x", "0x", "0xG1", "0b", "0b102", "1_", "1__0", "1_.5", "1._5",
+            "1.5_", "1e_5", "0x_ff", "3rd", "12.e3",
+        ] {
+            assert_scan(
+                source,
+                vec![
+                    Token::new(1, 1, TokenType::ErrorMalformedNumber, source),
+                    Token::new(1, source.len() + 1, TokenType::Eof, ""),
+                ],
+            );
+        }
+    }
 }
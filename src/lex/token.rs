@@ -1,14 +1,54 @@
 use super::TokenType;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Token<'a> {
     pub line: usize,
+    /// The 1-based column where the token starts on its first line.
+    pub column: usize,
     pub typ: TokenType,
     pub lexeme: &'a str,
+    /// The contents of a string literal, with any escapes replaced, or the
+    /// normalized name of an identifier that wasn't already in NFC.
+    pub value: Option<Cow<'a, str>>,
 }
 
 impl<'a> Token<'a> {
-    pub fn new(line: usize, typ: TokenType, lexeme: &'a str) -> Self {
-        Self { line, typ, lexeme }
+    pub fn new(line: usize, column: usize, typ: TokenType, lexeme: &'a str) -> Self {
+        Self {
+            line,
+            column,
+            typ,
+            lexeme,
+            value: None,
+        }
+    }
+
+    pub fn string(line: usize, column: usize, lexeme: &'a str, value: Cow<'a, str>) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::new(line, column, TokenType::String_, lexeme)
+        }
+    }
+
+    /// An identifier, with its name normalized to NFC so that names which
+    /// look the same are the same.
+    pub fn identifier(line: usize, column: usize, lexeme: &'a str) -> Self {
+        let value = match is_nfc_quick(lexeme.chars()) {
+            IsNormalized::Yes => None,
+            _ => Some(lexeme.nfc().collect::<String>())
+                .filter(|name| name != lexeme)
+                .map(Cow::Owned),
+        };
+        Self {
+            value,
+            ..Self::new(line, column, TokenType::Identifier, lexeme)
+        }
+    }
+
+    /// The name an identifier token refers to.
+    pub fn name(&self) -> &str {
+        self.value.as_deref().unwrap_or(self.lexeme)
     }
 }
@@ -14,6 +14,12 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
     // One or two character tokens.
     Bang,
     BangEqual,
@@ -21,28 +27,50 @@ pub enum TokenType {
     EqualEqual,
     Greater,
     GreaterEqual,
+    GreaterGreater,
     Less,
     LessEqual,
+    LessLess,
+    MinusEqual,
+    MinusMinus,
+    PlusEqual,
+    PlusPlus,
+    SlashEqual,
+    StarEqual,
+    StarStar,
+    Tilde,
+    TildeSlash,
     // Literals.
     Identifier,
     String_,
+    /// The part of an interpolated string up to a `${`.  The string goes on
+    /// after the expression's closing `}`, with another `Interpolation` or
+    /// a `String_` for its end.
+    Interpolation,
     Number,
     Comment,
+    DocComment,
     // Keywords.
     And,
+    As,
+    Catch,
     Class,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
     Eof,
@@ -50,6 +78,8 @@ pub enum TokenType {
     // Error
     ErrorUnknownToken,
     ErrorUnclosedString,
+    ErrorInvalidEscape,
+    ErrorUnterminatedComment,
     ErrorMalformedNumber,
 }
 
@@ -57,19 +87,25 @@ impl TokenType {
     pub fn get(lexeme: &str) -> Self {
         match lexeme {
             "and" => And,
+            "as" => As,
+            "catch" => Catch,
             "class" => Class,
             "else" => Else,
             "false" => False,
+            "finally" => Finally,
             "for" => For,
             "fun" => Fun,
             "if" => If,
+            "import" => Import,
             "nil" => Nil,
             "or" => Or,
             "print" => Print,
             "return" => Return,
             "super" => Super,
             "this" => This,
+            "throw" => Throw,
             "true" => True,
+            "try" => Try,
             "var" => Var,
             "while" => While,
             _ => Identifier,
@@ -1,12 +1,22 @@
 // use log::{debug, info, warn};
+pub mod debug;
+pub mod format;
 pub mod interpreter;
 pub mod lex;
+pub mod lint;
+pub mod lsp;
 pub mod parse;
 
-use interpreter::{Interpreter, RuntimeError};
+use interpreter::{
+    Coverage, FileLoader, Hook, Interpreter, InterruptHandle, ModuleLoader, Profile, RuntimeError,
+    Value,
+};
 use lex::Scanner;
-use parse::Stmt;
+use parse::prepare;
 use parse::{ParseError, Parser};
+use parse::{Stmt, StmtKind};
+use std::io::Write;
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -29,6 +39,7 @@ impl From<RuntimeError> for LoxError {
     }
 }
 
+#[derive(Default)]
 pub struct Lox {
     interpreter: Interpreter,
 }
@@ -40,16 +51,92 @@ impl Lox {
         }
     }
 
+    /// A runtime whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        Self {
+            interpreter: Interpreter::with_output(output),
+        }
+    }
+
+    /// A handle that cancels whatever `run` is currently executing.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interpreter.interrupt_handle()
+    }
+
+    /// Find the modules that programs import with `loader`, eg to serve them
+    /// from memory or search other directories.  By default they are files
+    /// in the current directory.
+    pub fn set_module_loader(&mut self, loader: Box<dyn ModuleLoader>) {
+        self.interpreter.set_module_loader(loader);
+    }
+
+    /// Treat programs as the module the loader names `path`, so that they
+    /// import modules relative to it and importing it again is a cycle.
+    pub fn set_main_module(&mut self, path: &str) -> Result<(), LoxError> {
+        Ok(self.interpreter.set_main_module(path)?)
+    }
+
+    /// Run programs as the script at `path`, loading its imports with
+    /// `FileLoader::for_script` and treating an import of it as a cycle.
+    pub fn set_script_path(&mut self, path: &str) -> Result<(), LoxError> {
+        let path = Path::new(path);
+        self.set_module_loader(Box::new(FileLoader::for_script(Some(path))));
+        // The loader looks in the script's own directory first.
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        self.set_main_module(file_name.unwrap_or_default())
+    }
+
+    /// Call `hook` before every statement, eg to debug the program.
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.interpreter.set_hook(hook);
+    }
+
+    /// Record how often and for how long each line and construct runs.
+    pub fn enable_profiling(&mut self) {
+        self.interpreter.enable_profiling();
+    }
+
+    /// Stop profiling and return what was recorded.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.interpreter.take_profile()
+    }
+
+    /// Record which statements and branches run.
+    pub fn enable_coverage(&mut self) {
+        self.interpreter.enable_coverage();
+    }
+
+    /// Stop recording coverage and return what was recorded.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.interpreter.take_coverage()
+    }
+
     pub fn run(&mut self, contents: &str) -> Result<(), LoxError> {
         let statements = self.parse(contents)?;
         self.interpret(&statements)?;
         Ok(())
     }
 
+    /// Run a line of REPL input.  If it is a single expression statement,
+    /// return its value so the REPL can echo it.
+    pub fn run_interactive(&mut self, contents: &str) -> Result<Option<Value>, LoxError> {
+        let statements = self.parse(contents)?;
+        match statements.as_slice() {
+            [stmt @ Stmt {
+                kind: StmtKind::Expression(_),
+                ..
+            }] => Ok(self.interpreter.evaluate_statement(stmt)?),
+            _ => {
+                self.interpret(&statements)?;
+                Ok(None)
+            }
+        }
+    }
+
     fn parse(&self, contents: &str) -> Result<Vec<Stmt>, ParseError> {
         let scanner = Scanner::new(contents);
         let mut parser = Parser::new(scanner);
-        parser.parse()
+        parser.parse().map(prepare)
     }
 
     fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
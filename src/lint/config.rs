@@ -0,0 +1,83 @@
+use super::{LintError, LintId, LintResult, Severity};
+use crate::lex::{Scanner, TokenType};
+use std::collections::HashMap;
+
+const DIRECTIVE: &str = "lint:";
+
+/// What to do with a lint's findings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Per-file lint settings.
+///
+/// Files configure lints with comments such as
+/// `// lint: allow(unused-local, shadowing)`, `// lint: warn(undeclared-name)`
+/// or `// lint: deny(self-assignment)`.  A directive applies to the whole
+/// file, and later directives override earlier ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<LintId, Level>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the lint directives from the comments in `source`.
+    pub fn from_source(source: &str) -> LintResult<Self> {
+        let mut config = Self::new();
+        let comments = Scanner::new(source).filter(|t| t.typ == TokenType::Comment);
+        for comment in comments {
+            let text = comment.lexeme.trim_start_matches('/').trim();
+            if let Some(directive) = text.strip_prefix(DIRECTIVE) {
+                config.apply_directive(directive.trim(), comment.line)?;
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, lint: LintId, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    /// The severity to report `lint` at, or None if it is allowed.
+    pub fn severity(&self, lint: LintId) -> Option<Severity> {
+        match self.levels.get(&lint) {
+            None => Some(lint.default_severity()),
+            Some(Level::Allow) => None,
+            Some(Level::Warn) => Some(Severity::Warning),
+            Some(Level::Deny) => Some(Severity::Error),
+        }
+    }
+
+    /// Apply a directive like `allow(unused-local, shadowing)`.
+    fn apply_directive(&mut self, directive: &str, line: usize) -> LintResult<()> {
+        let malformed = || LintError::MalformedDirective {
+            directive: directive.to_owned(),
+            line,
+        };
+        let (level, names) = directive
+            .strip_suffix(')')
+            .and_then(|d| d.split_once('('))
+            .ok_or_else(malformed)?;
+        let level = match level.trim() {
+            "allow" => Level::Allow,
+            "warn" => Level::Warn,
+            "deny" => Level::Deny,
+            _ => return Err(malformed()),
+        };
+        for name in names.split(',').map(str::trim) {
+            let lint = LintId::from_name(name).ok_or_else(|| LintError::UnknownLint {
+                name: name.to_owned(),
+                line,
+            })?;
+            self.set(lint, level);
+        }
+        Ok(())
+    }
+}
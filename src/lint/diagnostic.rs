@@ -0,0 +1,86 @@
+use std::fmt::{Display, Error as FmtError, Formatter};
+
+/// The lints the linter knows how to check.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LintId {
+    UndeclaredName,
+    UnusedLocal,
+    Shadowing,
+    SelfAssignment,
+    ConstantCondition,
+    UnreachableCode,
+    MismatchedComparison,
+}
+
+impl LintId {
+    pub const ALL: [LintId; 7] = [
+        LintId::UndeclaredName,
+        LintId::UnusedLocal,
+        LintId::Shadowing,
+        LintId::SelfAssignment,
+        LintId::ConstantCondition,
+        LintId::UnreachableCode,
+        LintId::MismatchedComparison,
+    ];
+
+    /// The name used to refer to this lint in output and configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintId::UndeclaredName => "undeclared-name",
+            LintId::UnusedLocal => "unused-local",
+            LintId::Shadowing => "shadowing",
+            LintId::SelfAssignment => "self-assignment",
+            LintId::ConstantCondition => "constant-condition",
+            LintId::UnreachableCode => "unreachable-code",
+            LintId::MismatchedComparison => "mismatched-comparison",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|id| id.name() == name)
+    }
+
+    /// How serious a finding is when the file doesn't configure the lint.
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            LintId::UndeclaredName => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub lint: LintId,
+    pub severity: Severity,
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        write!(
+            f,
+            "line {}: {}[{}]: {}",
+            self.line,
+            self.severity,
+            self.lint.name(),
+            self.message
+        )
+    }
+}
@@ -0,0 +1,12 @@
+use crate::parse::ParseError;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum LintError {
+    #[error("Unable to lint invalid source: {0}")]
+    Parse(#[from] ParseError),
+    #[error("Unknown lint '{name}' in configuration on line {line}.")]
+    UnknownLint { name: String, line: usize },
+    #[error("Malformed lint configuration on line {line}: '{directive}'.")]
+    MalformedDirective { directive: String, line: usize },
+}
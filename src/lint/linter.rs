@@ -0,0 +1,551 @@
+use super::{Diagnostic, LintConfig, LintId, LintResult};
+use crate::lex::Scanner;
+use crate::parse::visit::{walk_expr, walk_stmt, Visitor};
+use crate::parse::{BinaryOp, Expr, LValue, Literal, Parser, Stmt, StmtKind, Symbol, UnaryOp};
+use std::collections::HashMap;
+
+/// Parse and lint `source`, using the lint directives in its comments.
+pub fn lint_source(source: &str) -> LintResult<Vec<Diagnostic>> {
+    let config = LintConfig::from_source(source)?;
+    let stmts = Parser::new(Scanner::new(source)).parse()?;
+    Ok(Linter::new(config).lint(&stmts))
+}
+
+struct Local {
+    line: usize,
+    used: bool,
+}
+
+/// Static checks over a parsed (not lowered) program.
+pub struct Linter {
+    config: LintConfig,
+    diagnostics: Vec<Diagnostic>,
+    // Top-level declarations, by name, with the line of the first one.
+    globals: HashMap<Symbol, usize>,
+    // Block scopes, innermost last.
+    scopes: Vec<Vec<(Symbol, Local)>>,
+    // The line of the statement being checked.
+    line: usize,
+}
+
+impl Linter {
+    pub fn new(config: LintConfig) -> Self {
+        Self {
+            config,
+            diagnostics: Vec::new(),
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            line: 0,
+        }
+    }
+
+    /// Check `stmts`, returning the findings in source order.
+    pub fn lint(mut self, stmts: &[Stmt]) -> Vec<Diagnostic> {
+        // Globals can be used by code that runs after their declaration, even
+        // if it comes earlier in the file, so gather them all up front.
+        for stmt in stmts {
+            if let StmtKind::Var { name, .. } | StmtKind::Import { name, .. } = &stmt.kind {
+                self.globals.entry(name.clone()).or_insert(stmt.line);
+            }
+        }
+        self.statements(stmts);
+        self.diagnostics.sort_by_key(|d| d.line);
+        self.diagnostics
+    }
+
+    fn report(&mut self, lint: LintId, line: usize, message: String) {
+        if let Some(severity) = self.config.severity(lint) {
+            self.diagnostics.push(Diagnostic {
+                lint,
+                severity,
+                line,
+                message,
+            });
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr, keyword: &str) {
+        if is_constant(condition) {
+            self.report(
+                LintId::ConstantCondition,
+                self.line,
+                format!("The condition of this `{keyword}` is always the same."),
+            );
+        }
+        self.visit_expr(condition);
+    }
+
+    /// Check a sequence of statements, reporting the first one after a
+    /// statement that never finishes normally.
+    fn statements(&mut self, stmts: &[Stmt]) {
+        let mut reported = false;
+        for (idx, stmt) in stmts.iter().enumerate() {
+            if !reported && idx > 0 && terminates(&stmts[idx - 1]) {
+                self.unreachable(stmt);
+                reported = true;
+            }
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn unreachable(&mut self, stmt: &Stmt) {
+        self.report(
+            LintId::UnreachableCode,
+            stmt.line,
+            "This statement can never run.".to_owned(),
+        );
+    }
+
+    fn comparison(&mut self, left: &Expr, op: BinaryOp, right: &Expr) {
+        let (Some(left_type), Some(right_type)) = (literal_type(left), literal_type(right)) else {
+            return;
+        };
+        let message = match op {
+            BinaryOp::Equal | BinaryOp::NotEqual if left_type != right_type => format!(
+                "Comparing a {left_type} with a {right_type} is always {}.",
+                op == BinaryOp::NotEqual
+            ),
+            BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Less | BinaryOp::LessEqual
+                if left_type != "number" || right_type != "number" =>
+            {
+                format!("Ordering a {left_type} and a {right_type} is a runtime type error.")
+            }
+            _ => return,
+        };
+        self.report(LintId::MismatchedComparison, self.line, message);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("Unbalanced lint scopes");
+        for (name, local) in scope {
+            // A leading underscore marks a variable as intentionally unused.
+            if !local.used && !name.as_str().starts_with('_') {
+                self.report(
+                    LintId::UnusedLocal,
+                    local.line,
+                    format!("`{name}` is never read."),
+                );
+            }
+        }
+    }
+
+    fn declare(&mut self, name: Symbol, line: usize) {
+        if self.scopes.is_empty() {
+            return;
+        }
+        let enclosing = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(n, _)| *n == name)
+            .map(|(_, local)| local.line)
+            .or_else(|| self.globals.get(&name).copied());
+        if let Some(shadowed_line) = enclosing {
+            self.report(
+                LintId::Shadowing,
+                line,
+                format!("`{name}` shadows the variable declared on line {shadowed_line}."),
+            );
+        }
+        let scope = self.scopes.last_mut().expect("Checked non-empty above");
+        scope.push((name, Local { line, used: false }));
+    }
+
+    /// Look up `name`, marking the local it refers to as read if `is_read`.
+    fn resolve(&mut self, name: &Symbol, is_read: bool) {
+        let local = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|(n, _)| n == name);
+        match local {
+            Some((_, local)) => local.used |= is_read,
+            None if self.globals.contains_key(name) => (),
+            None => self.report(
+                LintId::UndeclaredName,
+                self.line,
+                format!("`{name}` is never declared."),
+            ),
+        }
+    }
+}
+
+impl Visitor for Linter {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.line = stmt.line;
+        match &stmt.kind {
+            StmtKind::Var {
+                name, initializer, ..
+            } => {
+                if let Some(init) = initializer {
+                    self.visit_expr(init);
+                }
+                self.declare(name.clone(), stmt.line);
+            }
+            StmtKind::Import { name, .. } => self.declare(name.clone(), stmt.line),
+            StmtKind::Block(stmts) => {
+                self.begin_scope();
+                self.statements(stmts);
+                self.end_scope();
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(condition, "if");
+                match literal_truthiness(condition) {
+                    Some(false) => self.unreachable(then_branch),
+                    Some(true) => {
+                        if let Some(else_branch) = else_branch {
+                            self.unreachable(else_branch);
+                        }
+                    }
+                    None => (),
+                }
+                self.visit_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                // `while (true)` is the idiomatic infinite loop.
+                if *condition != Expr::bool(true) {
+                    self.condition(condition, "while");
+                }
+                if literal_truthiness(condition) == Some(false) {
+                    self.unreachable(body);
+                }
+                self.visit_stmt(body);
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.begin_scope();
+                if let Some(init) = initializer {
+                    self.visit_stmt(init);
+                }
+                self.line = stmt.line;
+                if let Some(condition) = condition {
+                    // As with `while (true)`, an infinite loop is intended.
+                    if *condition != Expr::bool(true) {
+                        self.condition(condition, "for");
+                    }
+                    if literal_truthiness(condition) == Some(false) {
+                        self.unreachable(body);
+                    }
+                }
+                if let Some(incr) = increment {
+                    self.visit_expr(incr);
+                }
+                self.visit_stmt(body);
+                self.end_scope();
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_stmt(body);
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name.clone(), handler.line);
+                    self.visit_stmt(handler);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.visit_stmt(finally);
+                }
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary { left, op, right } => {
+                self.comparison(left, *op, right);
+                walk_expr(self, expr);
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(condition, "?:");
+                self.visit_expr(then_branch);
+                self.visit_expr(else_branch);
+            }
+            Expr::Variable { name, .. } => self.resolve(name, true),
+            Expr::Assign {
+                target: LValue::Variable { name, .. },
+                op,
+                expr,
+            } => {
+                if let (
+                    None,
+                    Expr::Variable {
+                        name: value_name, ..
+                    },
+                ) = (op, ungroup(expr))
+                {
+                    if value_name == name {
+                        self.report(
+                            LintId::SelfAssignment,
+                            self.line,
+                            format!("`{name}` is assigned to itself."),
+                        );
+                    }
+                }
+                self.visit_expr(expr);
+                // A compound assignment reads the variable too.
+                self.resolve(name, op.is_some());
+            }
+            Expr::Update {
+                target: LValue::Variable { name, .. },
+                ..
+            } => self.resolve(name, true),
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+fn ungroup(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Grouping(inner) => ungroup(inner),
+        _ => expr,
+    }
+}
+
+/// Whether `stmt` always ends in a `throw`, so nothing after it runs.
+fn terminates(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Throw(_) => true,
+        StmtKind::Block(stmts) => stmts.iter().any(terminates),
+        StmtKind::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => terminates(then_branch) && terminates(else_branch),
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            // Without a `catch`, whatever the body throws propagates.
+            let rethrows = catch
+                .as_ref()
+                .is_none_or(|(_, handler)| terminates(handler));
+            finally.as_deref().is_some_and(terminates) || (terminates(body) && rethrows)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `expr` is built only from literals, so always has the same value.
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_) => true,
+        Expr::Grouping(inner) | Expr::Unary { right: inner, .. } => is_constant(inner),
+        Expr::Interpolation(parts) => parts.iter().all(is_constant),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            is_constant(left) && is_constant(right)
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => is_constant(condition) && is_constant(then_branch) && is_constant(else_branch),
+        Expr::Variable { .. } | Expr::Assign { .. } | Expr::Update { .. } | Expr::Get { .. } => {
+            false
+        }
+    }
+}
+
+/// Whether `expr` is a literal that is always truthy or always falsey.
+fn literal_truthiness(expr: &Expr) -> Option<bool> {
+    match ungroup(expr) {
+        Expr::Literal(Literal::Nil) | Expr::Literal(Literal::Bool(false)) => Some(false),
+        Expr::Literal(_) => Some(true),
+        _ => None,
+    }
+}
+
+/// The type of `expr` if it is a literal, possibly negated or grouped.
+pub(crate) fn literal_type(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Literal(Literal::Nil) => Some("nil"),
+        Expr::Literal(Literal::Bool(_)) => Some("bool"),
+        Expr::Literal(Literal::Number(_)) => Some("number"),
+        Expr::Literal(Literal::String(_)) => Some("string"),
+        Expr::Grouping(inner) => literal_type(inner),
+        Expr::Unary {
+            op: UnaryOp::Minus,
+            right,
+        } => literal_type(right).filter(|t| *t == "number"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{LintError, Severity};
+
+    fn assert_lints(source: &str, expected: &[(LintId, usize)]) {
+        let actual: Vec<(LintId, usize)> = lint_source(source)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.lint, d.line))
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        assert_lints(
+            r#"
+            var a = 1;
+            {
+                var b = a + 1;
+                print b;
+            }
+            for (var i = 0; i < 3; i = i + 1) print i;
+            while (true) a = a + 1;
+            for (;;) a = a + 1;
+            for (; true;) a = a + 1;
+            "#,
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_lint_undeclared() {
+        assert_lints(
+            "var a = 1;\nif (a) {\n  print UnboundVar;\n}\nprint later;\nvar later;",
+            &[(LintId::UndeclaredName, 3)],
+        );
+        assert_lints(
+            "{ var a; a = 1; }\nb = 2;",
+            &[(LintId::UnusedLocal, 1), (LintId::UndeclaredName, 2)],
+        );
+    }
+
+    #[test]
+    fn test_lint_unused_local() {
+        assert_lints(
+            "{\n  var a = 1;\n  var _b = 2;\n}",
+            &[(LintId::UnusedLocal, 2)],
+        );
+        // Globals may be used by later input, eg in the REPL.
+        assert_lints("var a = 1;", &[]);
+        assert_lints(
+            "import \"lib.lox\";\n{\n  import \"lib.lox\" as l;\n}",
+            &[(LintId::UnusedLocal, 3)],
+        );
+        assert_lints(
+            "try {} catch (e) {}\ntry {} catch (_e) {}",
+            &[(LintId::UnusedLocal, 1)],
+        );
+    }
+
+    #[test]
+    fn test_lint_shadowing() {
+        assert_lints(
+            "var a = 1;\n{\n  var b = a;\n  {\n    var b = 2;\n    var a = b;\n    print a;\n  }\n  print b;\n}",
+            &[(LintId::Shadowing, 5), (LintId::Shadowing, 6)],
+        );
+    }
+
+    #[test]
+    fn test_lint_self_assignment() {
+        assert_lints("var a = 1;\na = (a);", &[(LintId::SelfAssignment, 2)]);
+    }
+
+    #[test]
+    fn test_lint_constant_condition() {
+        assert_lints(
+            "if (1 < 2) print 1;\nwhile (!nil) print 2;\nfor (; false;) print 3;\nprint 1 ? 2 : 3;",
+            &[
+                (LintId::ConstantCondition, 1),
+                (LintId::ConstantCondition, 2),
+                (LintId::ConstantCondition, 3),
+                (LintId::UnreachableCode, 3),
+                (LintId::ConstantCondition, 4),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_unreachable_code() {
+        assert_lints(
+            "if (false)\n  print 1;\nif (true) print 2;\nelse\n  print 3;\nwhile (nil) {\n}",
+            &[
+                (LintId::ConstantCondition, 1),
+                (LintId::UnreachableCode, 2),
+                (LintId::ConstantCondition, 3),
+                (LintId::UnreachableCode, 5),
+                (LintId::ConstantCondition, 6),
+                (LintId::UnreachableCode, 6),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_unreachable_after_throw() {
+        assert_lints(
+            "{\n  throw 1;\n  print 2;\n  print 3;\n}\nprint 4;",
+            &[(LintId::UnreachableCode, 3), (LintId::UnreachableCode, 6)],
+        );
+        assert_lints(
+            "if (a) throw 1; else { throw 2; }\nprint 3;\nvar a;",
+            &[(LintId::UnreachableCode, 2)],
+        );
+        // A `catch` that doesn't throw carries on.
+        assert_lints("try { throw 1; } catch (_e) {}\nprint 2;", &[]);
+        assert_lints(
+            "for (; false;)\n  print 1;",
+            &[(LintId::ConstantCondition, 1), (LintId::UnreachableCode, 2)],
+        );
+    }
+
+    #[test]
+    fn test_lint_mismatched_comparison() {
+        assert_lints(
+            "var a = 1;\nprint a == \"1\";\nprint 1 == \"1\";\nprint -1 < nil;\nprint 1 < 2;",
+            &[
+                (LintId::MismatchedComparison, 3),
+                (LintId::MismatchedComparison, 4),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let source = "// lint: allow(unused-local)\n// lint: deny(shadowing)\nvar a;\n{ var a; }";
+        let diagnostics = lint_source(source).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].lint, LintId::Shadowing);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        assert_eq!(
+            lint_source("// lint: allow(typo)\n"),
+            Err(LintError::UnknownLint {
+                name: "typo".to_owned(),
+                line: 1
+            })
+        );
+        assert!(matches!(
+            lint_source("// lint: ignore(shadowing)\n"),
+            Err(LintError::MalformedDirective { .. })
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+mod config;
+mod diagnostic;
+mod error;
+mod linter;
+
+pub use config::{Level, LintConfig};
+pub use diagnostic::{Diagnostic, LintId, Severity};
+pub use error::LintError;
+pub(crate) use linter::literal_type;
+pub use linter::{lint_source, Linter};
+
+type LintResult<T> = Result<T, LintError>;
@@ -0,0 +1,494 @@
+use crate::lex::{Scanner, Token, TokenType};
+use crate::lint::{literal_type, LintConfig, Linter, Severity};
+use crate::parse::visit::{walk_expr, walk_stmt, Visitor};
+use crate::parse::{Expr, LValue, Parser, Stmt, StmtKind};
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+/// The location of a name in the source: 1-based line and column, and its
+/// length.  Columns and lengths count UTF-16 code units, as LSP positions do.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Span {
+    fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.column <= column && column <= self.column + self.length
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// One appearance of a variable name in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occurrence {
+    pub name: String,
+    pub span: Span,
+    /// Index of the occurrence that declares this variable, if any.  A
+    /// declaration refers to itself.
+    pub declaration: Option<usize>,
+    /// For declarations, the type of the initializer if it is a literal.
+    pub literal_type: Option<&'static str>,
+    /// For declarations, their doc comment.
+    pub doc: Option<String>,
+}
+
+/// What the language server knows about one version of a document.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub problems: Vec<Problem>,
+    /// `var` declarations, found from tokens so that they are available even
+    /// while the document doesn't parse.
+    pub symbols: Vec<(String, Span)>,
+    occurrences: Vec<Occurrence>,
+    globals: Vec<String>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let mut analysis = Analysis {
+            symbols: declared_symbols(source),
+            ..Default::default()
+        };
+
+        let stmts = match Parser::new(Scanner::new(source)).parse() {
+            Ok(stmts) => stmts,
+            Err(err) => {
+                analysis.problems.push(Problem {
+                    line: err.line().unwrap_or_else(|| source.lines().count().max(1)),
+                    severity: Severity::Error,
+                    message: err.to_string(),
+                });
+                return analysis;
+            }
+        };
+
+        let config = LintConfig::from_source(source).unwrap_or_default();
+        for diagnostic in Linter::new(config).lint(&stmts) {
+            analysis.problems.push(Problem {
+                line: diagnostic.line,
+                severity: diagnostic.severity,
+                message: format!("{} [{}]", diagnostic.message, diagnostic.lint.name()),
+            });
+        }
+
+        let mut resolver = Resolver::new(source);
+        resolver.statements(&stmts);
+        analysis.occurrences = resolver.finish();
+        analysis.globals = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Var { name, .. } | StmtKind::Import { name, .. } => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+        analysis.globals.sort();
+        analysis.globals.dedup();
+        analysis
+    }
+
+    fn occurrence_at(&self, line: usize, column: usize) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|occ| occ.span.contains(line, column))
+    }
+
+    /// Where the variable at the given position is declared.
+    pub fn definition(&self, line: usize, column: usize) -> Option<Span> {
+        let decl = self.occurrence_at(line, column)?.declaration?;
+        Some(self.occurrences[decl].span)
+    }
+
+    /// Every appearance of the variable at the given position.
+    pub fn references(&self, line: usize, column: usize, include_declaration: bool) -> Vec<Span> {
+        let Some(occ) = self.occurrence_at(line, column) else {
+            return Vec::new();
+        };
+        let Some(decl) = occ.declaration else {
+            // Undeclared names: match by name among the other undeclared uses.
+            let name = occ.name.clone();
+            return self
+                .occurrences
+                .iter()
+                .filter(|o| o.declaration.is_none() && o.name == name)
+                .map(|o| o.span)
+                .collect();
+        };
+        self.occurrences
+            .iter()
+            .enumerate()
+            .filter(|(idx, o)| o.declaration == Some(decl) && (include_declaration || *idx != decl))
+            .map(|(_, o)| o.span)
+            .collect()
+    }
+
+    /// A description of the variable at the given position.
+    pub fn hover(&self, line: usize, column: usize) -> Option<(String, Span)> {
+        let occ = self.occurrence_at(line, column)?;
+        let text = match occ.declaration {
+            None => format!("{} (undeclared)", occ.name),
+            Some(decl) => {
+                let decl = &self.occurrences[decl];
+                let signature = match decl.literal_type {
+                    Some(typ) => format!("var {}: {typ}", decl.name),
+                    None => format!("var {}", decl.name),
+                };
+                match &decl.doc {
+                    Some(doc) => format!("{signature}\n\n{doc}"),
+                    None => signature,
+                }
+            }
+        };
+        Some((text, occ.span))
+    }
+
+    /// Names that can be completed anywhere: keywords and global variables.
+    pub fn completions(&self) -> Vec<(String, bool)> {
+        let keywords = KEYWORDS.iter().map(|kw| (kw.to_string(), true));
+        let globals = self.globals.iter().map(|name| (name.clone(), false));
+        keywords.chain(globals).collect()
+    }
+}
+
+pub const KEYWORDS: [&str; 22] = [
+    "and", "as", "catch", "class", "else", "false", "finally", "for", "fun", "if", "import", "nil",
+    "or", "print", "return", "super", "this", "throw", "true", "try", "var", "while",
+];
+
+/// Where `token` is, converting its column from characters to UTF-16.
+fn token_span(lines: &[&str], token: &Token) -> Span {
+    let line = lines.get(token.line - 1).copied().unwrap_or_default();
+    let before: usize = line
+        .chars()
+        .take(token.column - 1)
+        .map(char::len_utf16)
+        .sum();
+    Span {
+        line: token.line,
+        column: before + 1,
+        length: token.lexeme.encode_utf16().count(),
+    }
+}
+
+fn declared_symbols(source: &str) -> Vec<(String, Span)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut symbols = Vec::new();
+    let mut after_var = false;
+    for token in Scanner::new(source) {
+        if after_var && token.typ == TokenType::Identifier {
+            symbols.push((token.name().to_owned(), token_span(&lines, &token)));
+        }
+        after_var = matches!(token.typ, TokenType::Var | TokenType::As);
+    }
+    symbols
+}
+
+/// Matches the names in the AST to their identifier tokens and resolves each
+/// use to its declaration.
+///
+/// Every identifier token in the source is exactly one name in the AST, so
+/// walking the AST in source order visits names in the order of the tokens.
+/// Property names after a `.` are skipped rather than resolved.
+struct Resolver {
+    identifiers: Peekable<std::vec::IntoIter<(String, Span)>>,
+    occurrences: Vec<Occurrence>,
+    scopes: Vec<HashMap<String, usize>>,
+    globals: HashMap<String, usize>,
+    // Uses that didn't match a local, to be matched to globals at the end.
+    unresolved: Vec<usize>,
+}
+
+impl Resolver {
+    fn new(source: &str) -> Self {
+        let lines: Vec<&str> = source.lines().collect();
+        let identifiers: Vec<(String, Span)> = Scanner::new(source)
+            .filter(|t| t.typ == TokenType::Identifier)
+            .map(|t| (t.name().to_owned(), token_span(&lines, &t)))
+            .collect();
+        Self {
+            identifiers: identifiers.into_iter().peekable(),
+            occurrences: Vec::new(),
+            scopes: Vec::new(),
+            globals: HashMap::new(),
+            unresolved: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Occurrence> {
+        for idx in std::mem::take(&mut self.unresolved) {
+            let decl = self.globals.get(&self.occurrences[idx].name).copied();
+            self.occurrences[idx].declaration = decl;
+        }
+        self.occurrences
+    }
+
+    /// Record the next identifier if it is `name`.  A name with no token of
+    /// its own is skipped, leaving the token for the name that has it.
+    fn occurrence(&mut self, name: &str) -> Option<usize> {
+        let (token_name, span) = self.identifiers.next_if(|(token, _)| token == name)?;
+        self.occurrences.push(Occurrence {
+            name: token_name,
+            span,
+            declaration: None,
+            literal_type: None,
+            doc: None,
+        });
+        Some(self.occurrences.len() - 1)
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    /// Make the occurrence `idx` a declaration in the innermost scope.
+    fn declare(&mut self, idx: usize) {
+        self.occurrences[idx].declaration = Some(idx);
+        let name = self.occurrences[idx].name.clone();
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                scope.insert(name, idx);
+            }
+            None => {
+                self.globals.entry(name).or_insert(idx);
+            }
+        }
+    }
+
+    fn resolve(&mut self, idx: usize) {
+        let name = &self.occurrences[idx].name;
+        let local = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        match local {
+            Some(decl) => self.occurrences[idx].declaration = Some(*decl),
+            None => self.unresolved.push(idx),
+        }
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Var {
+                name,
+                initializer,
+                doc,
+            } => {
+                let occ = self.occurrence(name.as_str());
+                if let Some(init) = initializer {
+                    self.visit_expr(init);
+                }
+                let Some(idx) = occ else {
+                    return;
+                };
+                self.occurrences[idx].literal_type = initializer.as_ref().and_then(literal_type);
+                self.occurrences[idx].doc = doc.clone();
+                self.declare(idx);
+            }
+            // Only an `as` name is in the source.
+            StmtKind::Import {
+                name, alias: true, ..
+            } => {
+                if let Some(idx) = self.occurrence(name.as_str()) {
+                    self.occurrences[idx].literal_type = Some("module");
+                    self.declare(idx);
+                }
+            }
+            StmtKind::Block(_) | StmtKind::For { .. } => {
+                self.scopes.push(HashMap::new());
+                walk_stmt(self, stmt);
+                self.scopes.pop();
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_stmt(body);
+                if let Some((name, handler)) = catch {
+                    self.scopes.push(HashMap::new());
+                    if let Some(idx) = self.occurrence(name.as_str()) {
+                        self.declare(idx);
+                    }
+                    self.visit_stmt(handler);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.visit_stmt(finally);
+                }
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable { name, .. } => {
+                if let Some(idx) = self.occurrence(name.as_str()) {
+                    self.resolve(idx);
+                }
+            }
+            Expr::Get { object, .. } => {
+                self.visit_expr(object);
+                self.identifiers.next();
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    fn visit_lvalue(&mut self, target: &LValue) {
+        let LValue::Variable { name, .. } = target;
+        if let Some(idx) = self.occurrence(name.as_str()) {
+            self.resolve(idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "var a = 1;\n{\n  var a = \"inner\";\n  print a;\n}\nprint a + b;\na = 2;\nvar b;";
+
+    fn span(line: usize, column: usize) -> Span {
+        Span {
+            line,
+            column,
+            length: 1,
+        }
+    }
+
+    #[test]
+    fn test_symbols() {
+        let analysis = Analysis::new(SOURCE);
+        let names: Vec<(&str, Span)> = analysis
+            .symbols
+            .iter()
+            .map(|(name, span)| (name.as_str(), *span))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("a", span(1, 5)), ("a", span(3, 7)), ("b", span(8, 5))]
+        );
+    }
+
+    #[test]
+    fn test_definition() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(analysis.definition(4, 9), Some(span(3, 7)));
+        assert_eq!(analysis.definition(6, 7), Some(span(1, 5)));
+        assert_eq!(analysis.definition(7, 1), Some(span(1, 5)));
+        // Globals can be used before they are declared.
+        assert_eq!(analysis.definition(6, 11), Some(span(8, 5)));
+        assert_eq!(analysis.definition(2, 1), None);
+    }
+
+    #[test]
+    fn test_references() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(
+            analysis.references(1, 5, true),
+            vec![span(1, 5), span(6, 7), span(7, 1)]
+        );
+        assert_eq!(analysis.references(4, 9, false), vec![span(4, 9)]);
+    }
+
+    #[test]
+    fn test_hover() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(analysis.hover(4, 9).unwrap().0, "var a: string");
+        assert_eq!(analysis.hover(6, 7).unwrap().0, "var a: number");
+        assert_eq!(analysis.hover(6, 11).unwrap().0, "var b");
+        let analysis = Analysis::new("print c;");
+        assert_eq!(analysis.hover(1, 7).unwrap().0, "c (undeclared)");
+        // Negated literals have a type, as they do for the linter.
+        let analysis = Analysis::new("var n = -(1);");
+        assert_eq!(analysis.hover(1, 5).unwrap().0, "var n: number");
+    }
+
+    #[test]
+    fn test_hover_doc() {
+        let analysis = Analysis::new("/// The answer.\n/// Always.\nvar a = 42;\nprint a;");
+        assert_eq!(
+            analysis.hover(4, 7).unwrap().0,
+            "var a: number\n\nThe answer.\nAlways."
+        );
+    }
+
+    #[test]
+    fn test_unicode_names() {
+        // The declaration is decomposed and the use precomposed.
+        let analysis = Analysis::new("var cafe\u{301} = 1; print caf\u{e9};");
+        let declaration = Span {
+            line: 1,
+            column: 5,
+            length: 5,
+        };
+        assert_eq!(analysis.definition(1, 23), Some(declaration));
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        // `é` is one UTF-16 unit, and `😀` is two.
+        let analysis = Analysis::new("print \"é😀\"; var a = 1; print a;");
+        assert_eq!(analysis.definition(1, 31), Some(span(1, 18)));
+    }
+
+    #[test]
+    fn test_globals_deduplicated() {
+        let analysis = Analysis::new("var a; var b; var a;");
+        let globals = analysis.completions().into_iter().filter(|(_, kw)| !kw);
+        assert_eq!(globals.count(), 2);
+    }
+
+    #[test]
+    fn test_catch_names() {
+        let analysis = Analysis::new("var e;\ntry {} catch (e) { print e.line; }\nprint e;");
+        assert_eq!(analysis.definition(2, 26), Some(span(2, 15)));
+        assert_eq!(analysis.definition(3, 7), Some(span(1, 5)));
+        // Property names aren't variables.
+        assert_eq!(analysis.definition(2, 28), None);
+    }
+
+    #[test]
+    fn test_import_names() {
+        let analysis = Analysis::new("import \"a.lox\" as b;\nprint b.c;");
+        assert_eq!(analysis.definition(2, 7), Some(span(1, 19)));
+        assert_eq!(analysis.hover(2, 7).unwrap().0, "var b: module");
+        assert_eq!(analysis.symbols, vec![("b".to_owned(), span(1, 19))]);
+    }
+
+    #[test]
+    fn test_problems() {
+        let analysis = Analysis::new("var a = 1;\nprint (a;");
+        assert_eq!(analysis.problems.len(), 1);
+        assert_eq!(analysis.problems[0].line, 2);
+        assert_eq!(analysis.problems[0].severity, Severity::Error);
+        // Symbols are still available for a document that doesn't parse.
+        assert_eq!(analysis.symbols.len(), 1);
+
+        let analysis = Analysis::new("{ var unused; }");
+        assert_eq!(analysis.problems.len(), 1);
+        assert_eq!(analysis.problems[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_completions() {
+        let analysis = Analysis::new(SOURCE);
+        let completions = analysis.completions();
+        assert!(completions.contains(&("while".to_owned(), true)));
+        assert!(completions.contains(&("a".to_owned(), false)));
+        assert!(completions.contains(&("b".to_owned(), false)));
+    }
+}
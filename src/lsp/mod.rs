@@ -0,0 +1,8 @@
+//! A language server for Lox, speaking LSP over JSON-RPC.
+mod analysis;
+mod server;
+mod transport;
+
+pub use analysis::{Analysis, Span};
+pub use server::{parse_error, Server};
+pub use transport::{read_message, write_message};
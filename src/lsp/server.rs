@@ -0,0 +1,357 @@
+use super::analysis::{Analysis, Span};
+use crate::lint::Severity;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Handles language server messages for a set of open documents.
+///
+/// Positions on the wire are 0-based; internally lines and columns are
+/// 1-based, as they are everywhere else in the crate.  Either way columns
+/// count UTF-16 code units, LSP's default position encoding.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Analysis>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client has sent `exit`.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// The exit code the process should use, which indicates whether the
+    /// client shut down cleanly first.
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Handle one message, returning the responses and notifications to send.
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str();
+                let text = params["textDocument"]["text"].as_str();
+                return match (uri, text) {
+                    (Some(uri), Some(text)) => vec![self.update(uri, text)],
+                    _ => Vec::new(),
+                };
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole document.
+                let uri = params["textDocument"]["uri"].as_str();
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                return match (uri, text) {
+                    (Some(uri), Some(text)) => vec![self.update(uri, text)],
+                    _ => Vec::new(),
+                };
+            }
+            "textDocument/didClose" => {
+                let Some(uri) = params["textDocument"]["uri"].as_str() else {
+                    return Vec::new();
+                };
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                // Notifications we don't understand are ignored.
+                return match id {
+                    Some(id) => vec![error_response(id, METHOD_NOT_FOUND, "Method not found")],
+                    None => Vec::new(),
+                };
+            }
+        };
+
+        match (id, result) {
+            (Some(id), Some(result)) => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            (Some(id), None) => vec![error_response(id, INVALID_PARAMS, "Unknown document")],
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Value {
+        let analysis = Analysis::new(text);
+        let diagnostics = analysis
+            .problems
+            .iter()
+            .map(|problem| {
+                let line = problem.line.saturating_sub(1);
+                let line_length = text
+                    .lines()
+                    .nth(line)
+                    .map_or(0, |l| l.encode_utf16().count());
+                json!({
+                    "range": {
+                        "start": {"line": line, "character": 0},
+                        "end": {"line": line, "character": line_length},
+                    },
+                    "severity": match problem.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    },
+                    "source": "lox",
+                    "message": problem.message,
+                })
+            })
+            .collect();
+        self.documents.insert(uri.to_owned(), analysis);
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Analysis)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        Some((uri, self.documents.get(uri)?))
+    }
+
+    /// The document and 1-based position a request refers to.
+    fn position<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Analysis, usize, usize)> {
+        let (uri, analysis) = self.document(params)?;
+        let line = params["position"]["line"].as_u64()? as usize + 1;
+        let column = params["position"]["character"].as_u64()? as usize + 1;
+        Some((uri, analysis, line, column))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Option<Value> {
+        let (_, analysis) = self.document(params)?;
+        let symbols: Vec<Value> = analysis
+            .symbols
+            .iter()
+            .map(|(name, span)| {
+                json!({
+                    "name": name,
+                    "kind": 13,
+                    "range": range(span),
+                    "selectionRange": range(span),
+                })
+            })
+            .collect();
+        Some(Value::Array(symbols))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, analysis, line, column) = self.position(params)?;
+        Some(match analysis.definition(line, column) {
+            Some(span) => location(uri, &span),
+            None => Value::Null,
+        })
+    }
+
+    fn references(&self, params: &Value) -> Option<Value> {
+        let (uri, analysis, line, column) = self.position(params)?;
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let locations = analysis
+            .references(line, column, include_declaration)
+            .iter()
+            .map(|span| location(uri, span))
+            .collect();
+        Some(Value::Array(locations))
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (_, analysis, line, column) = self.position(params)?;
+        Some(match analysis.hover(line, column) {
+            Some((text, span)) => json!({
+                "contents": {"kind": "markdown", "value": format!("```lox\n{text}\n```")},
+                "range": range(&span),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (_, analysis) = self.document(params)?;
+        let items = analysis
+            .completions()
+            .into_iter()
+            .map(|(label, keyword)| json!({"label": label, "kind": if keyword { 14 } else { 6 }}))
+            .collect();
+        Some(Value::Array(items))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "documentSymbolProvider": true,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": {"name": "lox-lsp"},
+    })
+}
+
+fn range(span: &Span) -> Value {
+    let line = span.line - 1;
+    let start = span.column - 1;
+    json!({
+        "start": {"line": line, "character": start},
+        "end": {"line": line, "character": start + span.length},
+    })
+}
+
+fn location(uri: &str, span: &Span) -> Value {
+    json!({"uri": uri, "range": range(span)})
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+/// The response to a message that isn't valid JSON, whose id is unknown.
+pub fn parse_error(err: &serde_json::Error) -> Value {
+    error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {err}"))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///test.lox";
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "lox", "version": 1, "text": text}},
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, line: u64, character: u64) -> Value {
+        let mut responses = server.handle(json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": method,
+            "params": {
+                "textDocument": {"uri": URI},
+                "position": {"line": line, "character": character},
+            },
+        }));
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], 7);
+        responses.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn test_initialize() {
+        let mut server = Server::new();
+        let responses =
+            server.handle(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["hoverProvider"],
+            true
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = Server::new();
+        let notes = open(&mut server, "var a = 1;\nprint b;");
+        assert_eq!(notes[0]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = notes[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+        assert_eq!(diagnostics[0]["range"]["end"]["character"], 8);
+
+        let notes = server.handle(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": "var b;\nprint b;"}]},
+        }));
+        assert_eq!(notes[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut server = Server::new();
+        open(&mut server, "var total = 0;\ntotal = total + 1;");
+
+        let definition = request(&mut server, "textDocument/definition", 1, 9);
+        assert_eq!(definition["uri"], URI);
+        assert_eq!(
+            definition["range"]["start"],
+            json!({"line": 0, "character": 4})
+        );
+        assert_eq!(
+            definition["range"]["end"],
+            json!({"line": 0, "character": 9})
+        );
+
+        let references = request(&mut server, "textDocument/references", 0, 5);
+        assert_eq!(references.as_array().unwrap().len(), 3);
+
+        let hover = request(&mut server, "textDocument/hover", 1, 0);
+        assert_eq!(hover["contents"]["value"], "```lox\nvar total: number\n```");
+
+        let symbols = request(&mut server, "textDocument/documentSymbol", 0, 0);
+        assert_eq!(symbols[0]["name"], "total");
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        let mut server = Server::new();
+        let responses =
+            server.handle(json!({"jsonrpc": "2.0", "id": 3, "method": "workspace/symbol"}));
+        assert_eq!(responses[0]["error"]["code"], METHOD_NOT_FOUND);
+        assert!(server
+            .handle(json!({"jsonrpc": "2.0", "method": "$/cancelRequest"}))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_and_exit() {
+        let mut server = Server::new();
+        server.handle(json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"}));
+        assert!(!server.exited());
+        server.handle(json!({"jsonrpc": "2.0", "method": "exit"}));
+        assert!(server.exited());
+        assert_eq!(server.exit_code(), 0);
+    }
+}
@@ -0,0 +1,74 @@
+//! JSON-RPC message framing: each message is a `Content-Length` header, a
+//! blank line, then that many bytes of JSON.
+use serde_json::{Result as JsonResult, Value};
+use std::io::{BufRead, Error as IoError, ErrorKind, Result as IoResult, Write};
+
+/// Read the next message, or None at end of input.  A body that isn't valid
+/// JSON is returned as the inner error, since the stream is still in step and
+/// the next message can be read.
+pub fn read_message(reader: &mut impl BufRead) -> IoResult<Option<JsonResult<Value>>> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // Tolerate stray blank lines between messages.
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let length = value.trim().parse().map_err(|_| invalid(header))?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> IoResult<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+fn invalid(msg: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &json!({"id": 1})).unwrap();
+        write_message(&mut buf, &json!({"id": "é"})).unwrap();
+        let mut reader = std::io::Cursor::new(buf);
+        let mut next = || read_message(&mut reader).unwrap().map(Result::unwrap);
+        assert_eq!(next(), Some(json!({"id": 1})));
+        assert_eq!(next(), Some(json!({"id": "é"})));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn test_malformed_json() {
+        let mut buf = b"Content-Length: 5\r\n\r\n{\"id\"".to_vec();
+        write_message(&mut buf, &json!({"id": 2})).unwrap();
+        let mut reader = std::io::Cursor::new(buf);
+        assert!(read_message(&mut reader).unwrap().unwrap().is_err());
+        // The next message is still read.
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(message.unwrap(), json!({"id": 2}));
+    }
+}
@@ -1,38 +1,169 @@
-use anyhow::{bail, Result as AnyResult};
-use lox::Lox;
-use std::io::Write;
+use anyhow::{bail, Context, Result as AnyResult};
+use lox::debug::Console;
+use lox::format::format_source;
+use lox::interpreter::{FileLoader, RuntimeError};
+use lox::lex::Scanner;
+use lox::lint::{lint_source, Severity};
+use lox::parse::{json, sexpr, Parser};
+use lox::{Lox, LoxError};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: lox [script]
+       lox run [--profile] [--folded <file>] [--coverage <file>] <script>
+       lox debug <script>
+       lox ast [--json] <script>
+       lox fmt [--check] [files...]
+       lox lint <files...>";
 
 fn main() -> AnyResult<()> {
     let mut args = std::env::args();
     let _prog_name = args.next().expect("Expected program name in args");
-    let script_path_opt = args.next();
-    match args.next() {
-        None => (),
-        Some(_) => bail!("Usage: lox [script]"),
-    }
-    if let Some(script_path) = script_path_opt {
-        run_file(&script_path)
-    } else {
-        run_prompt()
+    let args: Vec<String> = args.collect();
+    match args.first().map(String::as_str) {
+        None => run_prompt(),
+        Some("run") if args.len() > 1 => run_instrumented(&args[1..]),
+        Some("ast") if args.len() > 1 => run_ast(&args[1..]),
+        Some("debug") if args.len() == 2 => run_debug(&args[1]),
+        Some("fmt") => run_format(&args[1..]),
+        Some("lint") if args.len() > 1 => run_lint(&args[1..]),
+        Some(script_path) if args.len() == 1 => run_file(script_path),
+        Some(_) => bail!(USAGE),
     }
 }
 
 fn run_file(script_path: &str) -> AnyResult<()> {
     let contents = std::fs::read_to_string(script_path)?;
     let mut runtime = Lox::new();
+    runtime.set_script_path(script_path)?;
     runtime.run(&contents)?;
     Ok(())
 }
 
+/// Run a script, optionally profiling it or measuring its coverage.
+/// `--profile` prints a report of the hottest lines and constructs to stderr,
+/// and `--folded` writes stacks for flamegraph tools to a file.
+/// `--coverage` writes an LCOV tracefile and prints a summary to stderr.
+fn run_instrumented(args: &[String]) -> AnyResult<()> {
+    let mut report = false;
+    let mut folded_path = None;
+    let mut lcov_path = None;
+    let mut script_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => report = true,
+            "--folded" => match args.next() {
+                Some(path) => folded_path = Some(path),
+                None => bail!(USAGE),
+            },
+            "--coverage" => match args.next() {
+                Some(path) => lcov_path = Some(path),
+                None => bail!(USAGE),
+            },
+            path if script_path.is_none() => script_path = Some(path),
+            _ => bail!(USAGE),
+        }
+    }
+    let Some(script_path) = script_path else {
+        bail!(USAGE);
+    };
+
+    let contents = std::fs::read_to_string(script_path)?;
+    let mut runtime = Lox::new();
+    runtime.set_script_path(script_path)?;
+    if report || folded_path.is_some() {
+        runtime.enable_profiling();
+    }
+    if lcov_path.is_some() {
+        runtime.enable_coverage();
+    }
+    let result = runtime.run(&contents);
+    if let Some(profile) = runtime.take_profile() {
+        if report {
+            eprint!("{}", profile.report());
+        }
+        if let Some(path) = folded_path {
+            std::fs::write(path, profile.folded())?;
+        }
+    }
+    if let (Some(coverage), Some(path)) = (runtime.take_coverage(), lcov_path) {
+        eprint!("{}", coverage.summary(script_path));
+        std::fs::write(path, coverage.lcov(script_path))?;
+    }
+    Ok(result?)
+}
+
+/// Print a script's syntax tree as S-expressions, or as JSON with `--json`.
+fn run_ast(args: &[String]) -> AnyResult<()> {
+    let (json, script_path) = match args {
+        [flag, path] if flag == "--json" => (true, path),
+        [path] => (false, path),
+        _ => bail!(USAGE),
+    };
+    let contents = std::fs::read_to_string(script_path)?;
+    let stmts = Parser::new(Scanner::new(&contents)).parse()?;
+    if json {
+        println!("{:#}", json::encode_program(&stmts));
+    } else {
+        print!("{}", sexpr::program(&stmts));
+    }
+    Ok(())
+}
+
+/// Run a script under the command-line debugger, which pauses before the
+/// first statement.
+fn run_debug(script_path: &str) -> AnyResult<()> {
+    let contents = std::fs::read_to_string(script_path)?;
+    let mut runtime = Lox::new();
+    runtime.set_script_path(script_path)?;
+    let console = Console::new(&contents, std::io::stdin().lock(), std::io::stdout());
+    runtime.set_hook(Box::new(console));
+    match runtime.run(&contents) {
+        Err(LoxError::Runtime(RuntimeError::Aborted)) => Ok(()),
+        res => Ok(res?),
+    }
+}
+
 fn run_prompt() -> AnyResult<()> {
     let mut runtime = Lox::new();
+    runtime.set_module_loader(Box::new(FileLoader::for_script(None)));
+    // Ctrl-C cancels the running statement rather than killing the session.
+    // At the prompt it exits if pressed twice in a row.
+    let interrupt = runtime.interrupt_handle();
+    let handler_interrupt = interrupt.clone();
+    let idle = Arc::new(AtomicBool::new(true));
+    let handler_idle = idle.clone();
+    ctrlc::set_handler(move || {
+        if !handler_idle.load(Ordering::Relaxed) {
+            handler_interrupt.interrupt();
+        } else if handler_interrupt.is_interrupted() {
+            std::process::exit(130);
+        } else {
+            // Nothing consumes this interrupt until the next line is read,
+            // so it marks the first press.
+            handler_interrupt.interrupt();
+            print!("\n(To exit, press Ctrl-C again or Ctrl-D)\n");
+            let _ = do_prompt();
+        }
+    })?;
+
     do_prompt()?;
     let lines = std::io::stdin().lines();
     for line_res in lines {
         let line = line_res?;
-        match runtime.run(&line) {
+        interrupt.reset();
+        idle.store(false, Ordering::Relaxed);
+        let result = runtime.run_interactive(&line);
+        // A Ctrl-C that arrived as the statement finished isn't a press at
+        // the prompt.
+        interrupt.reset();
+        idle.store(true, Ordering::Relaxed);
+        match result {
+            Ok(Some(value)) => println!("{}", value.repr()),
+            Ok(None) => (),
             Err(e) => println!("{e}"),
-            Ok(()) => (),
         }
         do_prompt()?;
     }
@@ -44,3 +175,67 @@ fn do_prompt() -> AnyResult<()> {
     std::io::stdout().flush()?;
     Ok(())
 }
+
+/// Format files in place, or stdin to stdout if no files are given.  With
+/// `--check`, report files that aren't formatted and fail instead.
+fn run_format(args: &[String]) -> AnyResult<()> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if paths.is_empty() {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents)?;
+        let formatted = format_source(&contents)?;
+        if check && formatted != contents {
+            bail!("<stdin> is not formatted");
+        }
+        if !check {
+            print!("{formatted}");
+        }
+        return Ok(());
+    }
+
+    let mut unformatted = 0;
+    for path in paths {
+        let contents = std::fs::read_to_string(path)?;
+        let formatted = format_source(&contents).with_context(|| format!("Formatting {path}"))?;
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            println!("{path} is not formatted");
+            unformatted += 1;
+        } else {
+            std::fs::write(path, formatted)?;
+        }
+    }
+    if unformatted > 0 {
+        bail!("{unformatted} file(s) need formatting");
+    }
+    Ok(())
+}
+
+/// Lint each file, printing its findings.  Fails if any finding is an error.
+fn run_lint(paths: &[String]) -> AnyResult<()> {
+    let mut errors = 0;
+    for path in paths {
+        let contents = std::fs::read_to_string(path)?;
+        let diagnostics = lint_source(&contents).with_context(|| format!("Linting {path}"))?;
+        for diagnostic in diagnostics {
+            if diagnostic.severity == Severity::Error {
+                errors += 1;
+            }
+            println!(
+                "{path}:{}: {}[{}]: {}",
+                diagnostic.line,
+                diagnostic.severity,
+                diagnostic.lint.name(),
+                diagnostic.message
+            );
+        }
+    }
+    if errors > 0 {
+        bail!("{errors} lint error(s)");
+    }
+    Ok(())
+}
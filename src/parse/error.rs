@@ -14,6 +14,8 @@ pub enum ParseError {
     },
     #[error("Invalid assignment target on line {line}.")]
     InvalidAssignment { line: usize },
+    #[error("Invalid number '{lexeme}' on line {line}.")]
+    InvalidNumber { line: usize, lexeme: String },
 }
 
 impl ParseError {
@@ -23,6 +25,16 @@ impl ParseError {
         }
     }
 
+    /// The source line the error was found on, if known.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ParseError::UnexpectedEnd { .. } => None,
+            ParseError::UnexpectedToken { line, .. }
+            | ParseError::InvalidAssignment { line }
+            | ParseError::InvalidNumber { line, .. } => Some(*line),
+        }
+    }
+
     pub fn wrong_token(token: &Token<'_>, msg: impl Into<String>) -> Self {
         ParseError::UnexpectedToken {
             actual: token.typ,
@@ -32,3 +44,19 @@ impl ParseError {
         }
     }
 }
+
+/// A JSON document that isn't a valid syntax tree.
+#[derive(Debug, Error, PartialEq)]
+pub enum DecodeError {
+    #[error("Expected {expected} at {path}.")]
+    Invalid { path: String, expected: String },
+}
+
+impl DecodeError {
+    pub fn invalid(path: impl Into<String>, expected: impl Into<String>) -> Self {
+        DecodeError::Invalid {
+            path: path.into(),
+            expected: expected.into(),
+        }
+    }
+}
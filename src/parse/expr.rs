@@ -1,3 +1,6 @@
+use super::Symbol;
+use std::rc::Rc;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expr {
     Unary {
@@ -11,16 +14,84 @@ pub enum Expr {
     },
     Grouping(Box<Expr>),
     Literal(Literal),
-    Variable(String),
+    Variable {
+        name: Symbol,
+        slot: Slot,
+    },
+    /// `target = expr`, or `target op= expr` for compound assignment.
     Assign {
-        name: String,
+        target: LValue,
+        op: Option<BinaryOp>,
         expr: Box<Expr>,
     },
+    /// `++target` or `target++`, and likewise `--`.  A prefix update gives
+    /// the new value and a postfix one the old.
+    Update {
+        target: LValue,
+        op: UpdateOp,
+        prefix: bool,
+    },
     Logical {
         left: Box<Expr>,
         op: LogicalOp,
         right: Box<Expr>,
     },
+    /// `object.name`
+    Get {
+        object: Box<Expr>,
+        name: Symbol,
+    },
+    /// A string literal with `${}` in it, as the expressions and pieces of
+    /// string to join together.
+    Interpolation(Vec<Expr>),
+    /// `condition ? then_branch : else_branch`
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+}
+
+/// Something that can be assigned to.
+///
+/// Only variables for now.  Property and index targets will hold their object
+/// and index expressions here, so that compound assignments and updates can
+/// evaluate them once and then both read and write through the result.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LValue {
+    Variable { name: Symbol, slot: Slot },
+}
+
+impl LValue {
+    pub fn var(name: impl Into<Symbol>) -> Self {
+        LValue::Variable {
+            name: name.into(),
+            slot: Slot::Dynamic,
+        }
+    }
+
+    /// The target that `expr` refers to, if it can be assigned to.
+    pub fn from_expr(expr: Expr) -> Option<Self> {
+        match expr {
+            Expr::Variable { name, slot } => Some(LValue::Variable { name, slot }),
+            _ => None,
+        }
+    }
+}
+
+/// Where a variable is stored at runtime, as worked out by `resolve`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Slot {
+    /// Not resolved: search the enclosing scopes by name.
+    #[default]
+    Dynamic,
+    /// The `index`th variable declared in the block `depth` blocks out from
+    /// the innermost one.
+    Local {
+        depth: usize,
+        index: usize,
+    },
+    Global,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -28,7 +99,7 @@ pub enum Literal {
     Nil,
     Bool(bool),
     Number(f64),
-    String(String),
+    String(Rc<str>),
 }
 
 impl Expr {
@@ -42,10 +113,13 @@ impl Expr {
         Expr::Literal(Literal::Number(f))
     }
     pub fn string(s: &str) -> Self {
-        Expr::Literal(Literal::String(s.to_owned()))
+        Expr::Literal(Literal::String(s.into()))
     }
     pub fn var(s: &str) -> Self {
-        Expr::Variable(s.to_owned())
+        Expr::Variable {
+            name: Symbol::intern(s),
+            slot: Slot::Dynamic,
+        }
     }
 
     pub fn unary(op: UnaryOp, right: Expr) -> Self {
@@ -67,13 +141,26 @@ impl Expr {
         Expr::Grouping(Box::new(expr))
     }
 
-    pub fn assign(name: impl Into<String>, expr: Expr) -> Self {
+    pub fn assign(name: impl Into<Symbol>, expr: Expr) -> Self {
         Expr::Assign {
-            name: name.into(),
+            target: LValue::var(name),
+            op: None,
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn compound_assign(target: LValue, op: BinaryOp, expr: Expr) -> Self {
+        Expr::Assign {
+            target,
+            op: Some(op),
             expr: Box::new(expr),
         }
     }
 
+    pub fn update(target: LValue, op: UpdateOp, prefix: bool) -> Self {
+        Expr::Update { target, op, prefix }
+    }
+
     pub fn logical(left: Expr, op: LogicalOp, right: Expr) -> Self {
         Expr::Logical {
             left: Box::new(left),
@@ -81,12 +168,30 @@ impl Expr {
             right: Box::new(right),
         }
     }
+
+    pub fn get(object: Expr, name: impl Into<Symbol>) -> Self {
+        Expr::Get {
+            object: Box::new(object),
+            name: name.into(),
+        }
+    }
+
+    pub fn conditional(condition: Expr, then_branch: Expr, else_branch: Expr) -> Self {
+        Expr::Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum BinaryOp {
     Mult,
     Div,
+    Mod,
+    FloorDiv,
+    Pow,
     Add,
     Sub,
     Greater,
@@ -95,12 +200,116 @@ pub enum BinaryOp {
     LessEqual,
     NotEqual,
     Equal,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BinaryOp {
+    pub const ALL: [BinaryOp; 18] = [
+        BinaryOp::Mult,
+        BinaryOp::Div,
+        BinaryOp::Mod,
+        BinaryOp::FloorDiv,
+        BinaryOp::Pow,
+        BinaryOp::Add,
+        BinaryOp::Sub,
+        BinaryOp::Greater,
+        BinaryOp::GreaterEqual,
+        BinaryOp::Less,
+        BinaryOp::LessEqual,
+        BinaryOp::NotEqual,
+        BinaryOp::Equal,
+        BinaryOp::BitAnd,
+        BinaryOp::BitOr,
+        BinaryOp::BitXor,
+        BinaryOp::ShiftLeft,
+        BinaryOp::ShiftRight,
+    ];
+
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Mult => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::FloorDiv => "~/",
+            BinaryOp::Pow => "**",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Equal => "==",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.symbol() == symbol)
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UnaryOp {
     Not,
     Minus,
+    BitNot,
+}
+
+impl UnaryOp {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Minus => "-",
+            UnaryOp::BitNot => "~",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        [UnaryOp::Not, UnaryOp::Minus, UnaryOp::BitNot]
+            .into_iter()
+            .find(|op| op.symbol() == symbol)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UpdateOp {
+    Increment,
+    Decrement,
+}
+
+impl UpdateOp {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UpdateOp::Increment => "++",
+            UpdateOp::Decrement => "--",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        [UpdateOp::Increment, UpdateOp::Decrement]
+            .into_iter()
+            .find(|op| op.symbol() == symbol)
+    }
+
+    /// The arithmetic the update does, with an operand of 1.
+    pub fn binary_op(&self) -> BinaryOp {
+        match self {
+            UpdateOp::Increment => BinaryOp::Add,
+            UpdateOp::Decrement => BinaryOp::Sub,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -108,3 +317,19 @@ pub enum LogicalOp {
     And,
     Or,
 }
+
+impl LogicalOp {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LogicalOp::And => "and",
+            LogicalOp::Or => "or",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        [LogicalOp::And, LogicalOp::Or]
+            .into_iter()
+            .find(|op| op.symbol() == symbol)
+    }
+}
@@ -0,0 +1,564 @@
+//! A stable JSON encoding of syntax trees, so other tools can read, generate
+//! or transform Lox programs.
+//!
+//! Every node is an object with a `"kind"` naming its variant.  Statements
+//! carry their source `"line"`.  Optional children are `null` when absent,
+//! and operators are written as they are in source, eg `"op": ">="`.
+//! JSON has no infinities or NaN, so a literal with one of those values is
+//! written as an object, eg `{"number": "inf"}`, `"-inf"` or `"nan"`.
+use super::{
+    BinaryOp, DecodeError, Expr, LValue, Literal, LogicalOp, Stmt, StmtKind, Symbol, UnaryOp,
+    UpdateOp,
+};
+use serde_json::{json, Map, Value};
+
+type DecodeResult<T> = Result<T, DecodeError>;
+
+pub fn encode_program(stmts: &[Stmt]) -> Value {
+    Value::Array(stmts.iter().map(encode_stmt).collect())
+}
+
+pub fn encode_stmt(stmt: &Stmt) -> Value {
+    let line = stmt.line;
+    match &stmt.kind {
+        StmtKind::Expression(expr) => {
+            json!({"kind": "expression", "line": line, "expr": encode_expr(expr)})
+        }
+        StmtKind::Print(expr) => json!({"kind": "print", "line": line, "expr": encode_expr(expr)}),
+        StmtKind::Var {
+            name,
+            initializer,
+            doc,
+        } => json!({
+            "kind": "var",
+            "line": line,
+            "name": name.as_str(),
+            "initializer": initializer.as_ref().map(encode_expr),
+            "doc": doc,
+        }),
+        StmtKind::Block(stmts) => {
+            json!({"kind": "block", "line": line, "body": encode_program(stmts)})
+        }
+        StmtKind::Import { path, name, alias } => json!({
+            "kind": "import",
+            "line": line,
+            "path": &**path,
+            "name": name.as_str(),
+            "alias": alias,
+        }),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => json!({
+            "kind": "if",
+            "line": line,
+            "condition": encode_expr(condition),
+            "then": encode_stmt(then_branch),
+            "else": else_branch.as_deref().map(encode_stmt),
+        }),
+        StmtKind::While { condition, body } => json!({
+            "kind": "while",
+            "line": line,
+            "condition": encode_expr(condition),
+            "body": encode_stmt(body),
+        }),
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => json!({
+            "kind": "for",
+            "line": line,
+            "initializer": initializer.as_deref().map(encode_stmt),
+            "condition": condition.as_ref().map(encode_expr),
+            "increment": increment.as_ref().map(encode_expr),
+            "body": encode_stmt(body),
+        }),
+        StmtKind::Throw(expr) => json!({"kind": "throw", "line": line, "value": encode_expr(expr)}),
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => json!({
+            "kind": "try",
+            "line": line,
+            "body": encode_stmt(body),
+            "catch": catch.as_ref().map(|(name, handler)| json!({
+                "name": name.as_str(),
+                "body": encode_stmt(handler),
+            })),
+            "finally": finally.as_deref().map(encode_stmt),
+        }),
+    }
+}
+
+fn encode_number(n: f64) -> Value {
+    if n.is_finite() {
+        json!(n)
+    } else if n.is_nan() {
+        json!({"number": "nan"})
+    } else {
+        json!({"number": if n > 0. { "inf" } else { "-inf" }})
+    }
+}
+
+pub fn encode_expr(expr: &Expr) -> Value {
+    match expr {
+        Expr::Literal(lit) => {
+            let value = match lit {
+                Literal::Nil => Value::Null,
+                Literal::Bool(b) => json!(b),
+                Literal::Number(n) => encode_number(*n),
+                Literal::String(s) => json!(&**s),
+            };
+            json!({"kind": "literal", "value": value})
+        }
+        Expr::Grouping(expr) => json!({"kind": "grouping", "expr": encode_expr(expr)}),
+        Expr::Interpolation(parts) => json!({
+            "kind": "interpolation",
+            "parts": parts.iter().map(encode_expr).collect::<Vec<_>>(),
+        }),
+        Expr::Unary { op, right } => json!({
+            "kind": "unary",
+            "op": op.symbol(),
+            "right": encode_expr(right),
+        }),
+        Expr::Binary { left, op, right } => json!({
+            "kind": "binary",
+            "op": op.symbol(),
+            "left": encode_expr(left),
+            "right": encode_expr(right),
+        }),
+        Expr::Logical { left, op, right } => json!({
+            "kind": "logical",
+            "op": op.symbol(),
+            "left": encode_expr(left),
+            "right": encode_expr(right),
+        }),
+        Expr::Variable { name, .. } => json!({"kind": "variable", "name": name.as_str()}),
+        Expr::Get { object, name } => json!({
+            "kind": "get",
+            "object": encode_expr(object),
+            "name": name.as_str(),
+        }),
+        Expr::Assign { target, op, expr } => json!({
+            "kind": "assign",
+            "target": encode_target(target),
+            "op": op.map(|op| op.symbol()),
+            "value": encode_expr(expr),
+        }),
+        Expr::Update { target, op, prefix } => json!({
+            "kind": "update",
+            "target": encode_target(target),
+            "op": op.symbol(),
+            "prefix": prefix,
+        }),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => json!({
+            "kind": "conditional",
+            "condition": encode_expr(condition),
+            "then": encode_expr(then_branch),
+            "else": encode_expr(else_branch),
+        }),
+    }
+}
+
+fn encode_target(target: &LValue) -> Value {
+    match target {
+        LValue::Variable { name, .. } => json!({"kind": "variable", "name": name.as_str()}),
+    }
+}
+
+/// Decode a program as `Parser::parse` would produce it.  Pass it through
+/// `parse::prepare` before running it.
+pub fn decode_program(value: &Value) -> DecodeResult<Vec<Stmt>> {
+    decode_stmts(value, "$")
+}
+
+pub fn decode_stmt(value: &Value) -> DecodeResult<Stmt> {
+    Node::new(value, "$".to_owned())?.stmt()
+}
+
+pub fn decode_expr(value: &Value) -> DecodeResult<Expr> {
+    Node::new(value, "$".to_owned())?.expr()
+}
+
+fn decode_stmts(value: &Value, path: &str) -> DecodeResult<Vec<Stmt>> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| DecodeError::invalid(path, "an array of statements"))?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| Node::new(item, format!("{path}[{idx}]"))?.stmt())
+        .collect()
+}
+
+/// A JSON object being decoded, and where it is in the document for errors.
+struct Node<'a> {
+    fields: &'a Map<String, Value>,
+    path: String,
+}
+
+impl<'a> Node<'a> {
+    fn new(value: &'a Value, path: String) -> DecodeResult<Self> {
+        match value.as_object() {
+            Some(fields) => Ok(Self { fields, path }),
+            None => Err(DecodeError::invalid(path, "an object")),
+        }
+    }
+
+    fn path(&self, field: &str) -> String {
+        format!("{}.{field}", self.path)
+    }
+
+    fn field(&self, field: &str) -> &'a Value {
+        self.fields.get(field).unwrap_or(&Value::Null)
+    }
+
+    fn string(&self, field: &str) -> DecodeResult<&'a str> {
+        self.field(field)
+            .as_str()
+            .ok_or_else(|| DecodeError::invalid(self.path(field), "a string"))
+    }
+
+    /// A string that may be `null` or missing.
+    fn optional_string(&self, field: &str) -> DecodeResult<Option<&'a str>> {
+        match self.field(field) {
+            Value::Null => Ok(None),
+            value => value
+                .as_str()
+                .map(Some)
+                .ok_or_else(|| DecodeError::invalid(self.path(field), "a string")),
+        }
+    }
+
+    fn line(&self) -> DecodeResult<usize> {
+        self.field("line")
+            .as_u64()
+            .map(|line| line as usize)
+            .ok_or_else(|| DecodeError::invalid(self.path("line"), "a line number"))
+    }
+
+    fn child(&self, field: &str) -> DecodeResult<Node<'a>> {
+        Node::new(self.field(field), self.path(field))
+    }
+
+    /// A child node that may be `null` or missing.
+    fn optional(&self, field: &str) -> DecodeResult<Option<Node<'a>>> {
+        match self.field(field) {
+            Value::Null => Ok(None),
+            value => Node::new(value, self.path(field)).map(Some),
+        }
+    }
+
+    fn stmt(&self) -> DecodeResult<Stmt> {
+        let line = self.line()?;
+        let kind = match self.string("kind")? {
+            "expression" => StmtKind::Expression(self.child("expr")?.expr()?),
+            "print" => StmtKind::Print(self.child("expr")?.expr()?),
+            "var" => StmtKind::Var {
+                name: Symbol::intern(self.string("name")?),
+                initializer: self
+                    .optional("initializer")?
+                    .map(|n| n.expr())
+                    .transpose()?,
+                doc: self.optional_string("doc")?.map(str::to_owned),
+            },
+            "block" => StmtKind::Block(decode_stmts(self.field("body"), &self.path("body"))?),
+            "import" => StmtKind::Import {
+                path: self.string("path")?.into(),
+                name: Symbol::intern(self.string("name")?),
+                alias: self
+                    .field("alias")
+                    .as_bool()
+                    .ok_or_else(|| DecodeError::invalid(self.path("alias"), "a boolean"))?,
+            },
+            "if" => StmtKind::If {
+                condition: self.child("condition")?.expr()?,
+                then_branch: Box::new(self.child("then")?.stmt()?),
+                else_branch: self
+                    .optional("else")?
+                    .map(|n| n.stmt().map(Box::new))
+                    .transpose()?,
+            },
+            "while" => StmtKind::While {
+                condition: self.child("condition")?.expr()?,
+                body: Box::new(self.child("body")?.stmt()?),
+            },
+            "for" => StmtKind::For {
+                initializer: self
+                    .optional("initializer")?
+                    .map(|n| n.stmt().map(Box::new))
+                    .transpose()?,
+                condition: self.optional("condition")?.map(|n| n.expr()).transpose()?,
+                increment: self.optional("increment")?.map(|n| n.expr()).transpose()?,
+                body: Box::new(self.child("body")?.stmt()?),
+            },
+            "throw" => StmtKind::Throw(self.child("value")?.expr()?),
+            "try" => StmtKind::Try {
+                body: Box::new(self.child("body")?.stmt()?),
+                catch: self
+                    .optional("catch")?
+                    .map(|n| -> DecodeResult<_> {
+                        let name = Symbol::intern(n.string("name")?);
+                        Ok((name, Box::new(n.child("body")?.stmt()?)))
+                    })
+                    .transpose()?,
+                finally: self
+                    .optional("finally")?
+                    .map(|n| n.stmt().map(Box::new))
+                    .transpose()?,
+            },
+            _ => return Err(DecodeError::invalid(self.path("kind"), "a statement kind")),
+        };
+        Ok(Stmt::new(line, kind))
+    }
+
+    fn target(&self) -> DecodeResult<LValue> {
+        match self.string("kind")? {
+            "variable" => Ok(LValue::var(self.string("name")?)),
+            _ => Err(DecodeError::invalid(
+                self.path("kind"),
+                "an assignment target",
+            )),
+        }
+    }
+
+    fn expr(&self) -> DecodeResult<Expr> {
+        let expr = match self.string("kind")? {
+            "literal" => Expr::Literal(match self.field("value") {
+                Value::Null => Literal::Nil,
+                Value::Bool(b) => Literal::Bool(*b),
+                Value::String(s) => Literal::String(s.as_str().into()),
+                Value::Number(n) => Literal::Number(n.as_f64().unwrap_or(f64::NAN)),
+                Value::Object(obj) => match obj.get("number").and_then(Value::as_str) {
+                    Some("inf") => Literal::Number(f64::INFINITY),
+                    Some("-inf") => Literal::Number(f64::NEG_INFINITY),
+                    Some("nan") => Literal::Number(f64::NAN),
+                    _ => return Err(DecodeError::invalid(self.path("value"), "a literal value")),
+                },
+                _ => return Err(DecodeError::invalid(self.path("value"), "a literal value")),
+            }),
+            "grouping" => Expr::group(self.child("expr")?.expr()?),
+            "interpolation" => {
+                let path = self.path("parts");
+                let parts = self
+                    .field("parts")
+                    .as_array()
+                    .ok_or_else(|| DecodeError::invalid(&path, "an array of expressions"))?;
+                Expr::Interpolation(
+                    parts
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, part)| Node::new(part, format!("{path}[{idx}]"))?.expr())
+                        .collect::<DecodeResult<_>>()?,
+                )
+            }
+            "unary" => {
+                let op = UnaryOp::from_symbol(self.string("op")?)
+                    .ok_or_else(|| DecodeError::invalid(self.path("op"), "a unary operator"))?;
+                Expr::unary(op, self.child("right")?.expr()?)
+            }
+            "binary" => {
+                let op = BinaryOp::from_symbol(self.string("op")?)
+                    .ok_or_else(|| DecodeError::invalid(self.path("op"), "a binary operator"))?;
+                Expr::binary(
+                    self.child("left")?.expr()?,
+                    op,
+                    self.child("right")?.expr()?,
+                )
+            }
+            "logical" => {
+                let op = LogicalOp::from_symbol(self.string("op")?)
+                    .ok_or_else(|| DecodeError::invalid(self.path("op"), "a logical operator"))?;
+                Expr::logical(
+                    self.child("left")?.expr()?,
+                    op,
+                    self.child("right")?.expr()?,
+                )
+            }
+            "variable" => Expr::var(self.string("name")?),
+            "get" => Expr::get(self.child("object")?.expr()?, self.string("name")?),
+            "assign" => Expr::Assign {
+                target: self.child("target")?.target()?,
+                op: self
+                    .optional_string("op")?
+                    .map(|op| {
+                        BinaryOp::from_symbol(op).ok_or_else(|| {
+                            DecodeError::invalid(self.path("op"), "a binary operator")
+                        })
+                    })
+                    .transpose()?,
+                expr: Box::new(self.child("value")?.expr()?),
+            },
+            "update" => {
+                let op = UpdateOp::from_symbol(self.string("op")?)
+                    .ok_or_else(|| DecodeError::invalid(self.path("op"), "`++` or `--`"))?;
+                let prefix = self
+                    .field("prefix")
+                    .as_bool()
+                    .ok_or_else(|| DecodeError::invalid(self.path("prefix"), "a boolean"))?;
+                Expr::update(self.child("target")?.target()?, op, prefix)
+            }
+            "conditional" => Expr::conditional(
+                self.child("condition")?.expr()?,
+                self.child("then")?.expr()?,
+                self.child("else")?.expr()?,
+            ),
+            _ => {
+                return Err(DecodeError::invalid(
+                    self.path("kind"),
+                    "an expression kind",
+                ))
+            }
+        };
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::lex::Scanner;
+    use crate::parse::{prepare, Parser};
+
+    const SOURCE: &str = r#"import "lib.lox";
+import "util/lib.lox" as util;
+var a = "one";
+var b;
+{
+  b = -(1 + 2) * 3 >= 4 or !nil and a != a;
+}
+if (b) print a; else print false;
+print b ? a : nil;
+a += 1;
+print a++ - --b;
+print "a=${a}, b=${"${b}"}";
+while (false) {}
+for (var i = 0; i < 2; i = i + 1) print i;
+for (;;) {}
+try { throw a; } catch (e) { print e.message; } finally {}
+try {} finally { print a; }"#;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        Parser::new(Scanner::new(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let stmts = parse(SOURCE);
+        let text = encode_program(&stmts).to_string();
+        let decoded = decode_program(&serde_json::from_str(&text).unwrap()).unwrap();
+        assert_eq!(decoded, stmts);
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let stmts = parse("print 1e400;");
+        let text = encode_program(&stmts).to_string();
+        assert!(text.contains(r#"{"number":"inf"}"#));
+        let decoded = decode_program(&serde_json::from_str(&text).unwrap()).unwrap();
+        assert_eq!(decoded, stmts);
+
+        let nan = encode_expr(&Expr::Literal(Literal::Number(f64::NAN)));
+        assert!(matches!(
+            decode_expr(&nan),
+            Ok(Expr::Literal(Literal::Number(n))) if n.is_nan()
+        ));
+        assert_eq!(
+            decode_expr(&json!({"kind": "literal", "value": {"number": "big"}})),
+            Err(DecodeError::invalid("$.value", "a literal value"))
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let stmts = parse("print x = 1 < 2;");
+        assert_eq!(
+            encode_stmt(&stmts[0]),
+            json!({
+                "kind": "print",
+                "line": 1,
+                "expr": {
+                    "kind": "assign",
+                    "target": {"kind": "variable", "name": "x"},
+                    "op": null,
+                    "value": {
+                        "kind": "binary",
+                        "op": "<",
+                        "left": {"kind": "literal", "value": 1.0},
+                        "right": {"kind": "literal", "value": 2.0},
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            decode_program(&json!({})),
+            Err(DecodeError::invalid("$", "an array of statements"))
+        );
+        assert_eq!(
+            decode_program(
+                &json!([{"kind": "print", "line": 1, "expr": {"kind": "unary", "op": "+"}}])
+            ),
+            Err(DecodeError::invalid("$[0].expr.op", "a unary operator"))
+        );
+        assert_eq!(
+            decode_stmt(&json!({"kind": "block", "body": []})),
+            Err(DecodeError::invalid("$.line", "a line number"))
+        );
+        assert_eq!(
+            decode_expr(&json!({"kind": "call"})),
+            Err(DecodeError::invalid("$.kind", "an expression kind"))
+        );
+    }
+
+    #[test]
+    fn test_run_decoded() {
+        let program = json!([
+            {"kind": "var", "line": 1, "name": "a", "initializer": {"kind": "literal", "value": 2}},
+            {"kind": "expression", "line": 2, "expr": {
+                "kind": "assign",
+                "target": {"kind": "variable", "name": "a"},
+                "value": {"kind": "binary", "op": "*", "left": {"kind": "variable", "name": "a"}, "right": {"kind": "literal", "value": 20}},
+            }},
+            {"kind": "expression", "line": 3, "expr": {
+                "kind": "assign",
+                "target": {"kind": "variable", "name": "a"},
+                "op": "+",
+                "value": {"kind": "literal", "value": 1},
+            }},
+            {"kind": "expression", "line": 4, "expr": {
+                "kind": "update",
+                "target": {"kind": "variable", "name": "a"},
+                "op": "++",
+                "prefix": false,
+            }},
+        ]);
+        let stmts = decode_program(&program).unwrap();
+        let mut interp = Interpreter::with_output(Box::new(std::io::sink()));
+        interp.interpret(&prepare(stmts)).unwrap();
+        assert_eq!(
+            interp.environment().get(&Symbol::intern("a")),
+            Ok(&crate::interpreter::Value::Number(42.))
+        );
+
+        let program = encode_program(&parse("var n = 0; for (var i = 0; i < 4; i++) n += i;"));
+        let stmts = decode_program(&program).unwrap();
+        interp.interpret(&prepare(stmts)).unwrap();
+        assert_eq!(
+            interp.environment().get(&Symbol::intern("n")),
+            Ok(&crate::interpreter::Value::Number(6.))
+        );
+    }
+}
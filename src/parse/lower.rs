@@ -0,0 +1,117 @@
+//! Lowering from the surface AST produced by `Parser` to the core AST run by
+//! `Interpreter`.
+//!
+//! The surface AST keeps syntactic sugar such as `for` loops so that tooling
+//! can see what was written.  Lowering rewrites that sugar in terms of core
+//! statements, so the interpreter only has to understand the core.
+use super::visit::{fold_stmt, Fold};
+use super::{Expr, Stmt, StmtKind};
+
+pub fn lower(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    stmts.into_iter().map(lower_stmt).collect()
+}
+
+pub fn lower_stmt(stmt: Stmt) -> Stmt {
+    Lower.fold_stmt(stmt)
+}
+
+struct Lower;
+
+impl Fold for Lower {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        let line = stmt.line;
+        match stmt.kind {
+            // `for (init; cond; incr) body` becomes
+            // `{ init; while (cond) { body; incr; } }`
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                let mut body = self.fold_stmt(*body);
+                if let Some(incr) = increment {
+                    body = Stmt::block(line, vec![body, Stmt::expression(line, incr)]);
+                }
+                let condition = condition.unwrap_or(Expr::bool(true));
+                let mut lowered = Stmt::while_loop(line, condition, body);
+                if let Some(init) = initializer {
+                    lowered = Stmt::block(line, vec![self.fold_stmt(*init), lowered]);
+                }
+                lowered
+            }
+            kind => fold_stmt(self, Stmt::new(line, kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Scanner;
+    use crate::parse::{BinaryOp, Parser};
+
+    fn assert_lower(source: &str, expected: Stmt) {
+        let stmt = Parser::new(Scanner::new(source)).declaration().unwrap();
+        assert_eq!(lower_stmt(stmt), expected);
+    }
+
+    #[test]
+    fn test_lower_for() {
+        assert_lower(
+            "for (var i = 0; i < 2; i = i + 1)\n  print i;",
+            Stmt::block(
+                1,
+                vec![
+                    Stmt::var(1, "i", Some(Expr::number(0.))),
+                    Stmt::while_loop(
+                        1,
+                        Expr::binary(Expr::var("i"), BinaryOp::Less, Expr::number(2.)),
+                        Stmt::block(
+                            1,
+                            vec![
+                                Stmt::print(2, Expr::var("i")),
+                                Stmt::expression(
+                                    1,
+                                    Expr::assign(
+                                        "i",
+                                        Expr::binary(
+                                            Expr::var("i"),
+                                            BinaryOp::Add,
+                                            Expr::number(1.),
+                                        ),
+                                    ),
+                                ),
+                            ],
+                        ),
+                    ),
+                ],
+            ),
+        );
+    }
+
+    #[test]
+    fn test_lower_empty_for() {
+        assert_lower(
+            "for (;;) 1;",
+            Stmt::while_loop(1, Expr::bool(true), Stmt::expression(1, Expr::number(1.))),
+        );
+    }
+
+    #[test]
+    fn test_lower_nested_for() {
+        assert_lower(
+            "if (true) for (;;) for (;;) 1;",
+            Stmt::if_else(
+                1,
+                Expr::bool(true),
+                Stmt::while_loop(
+                    1,
+                    Expr::bool(true),
+                    Stmt::while_loop(1, Expr::bool(true), Stmt::expression(1, Expr::number(1.))),
+                ),
+                None,
+            ),
+        );
+    }
+}
@@ -1,11 +1,23 @@
 mod error;
 mod expr;
+pub mod json;
+pub mod lower;
 mod parser;
+pub mod resolve;
+pub mod sexpr;
 mod stmt;
+mod symbol;
+pub mod visit;
 
-pub use error::ParseError;
-pub use expr::{BinaryOp, Expr, Literal, LogicalOp, UnaryOp};
+pub use error::{DecodeError, ParseError};
+pub use expr::{BinaryOp, Expr, LValue, Literal, LogicalOp, Slot, UnaryOp, UpdateOp};
 pub use parser::Parser;
-pub use stmt::Stmt;
+pub use stmt::{Stmt, StmtKind};
+pub use symbol::{Symbol, SymbolMap};
 
 type ParseResult<T> = Result<T, ParseError>;
+
+/// Lower and resolve a parsed or decoded program, ready for the interpreter.
+pub fn prepare(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    resolve::resolve(lower::lower(stmts))
+}
@@ -1,22 +1,33 @@
-use super::{BinaryOp, Expr, LogicalOp, ParseError, ParseResult, Stmt, UnaryOp};
+use super::{
+    BinaryOp, Expr, LValue, LogicalOp, ParseError, ParseResult, Stmt, StmtKind, Symbol, UnaryOp,
+    UpdateOp,
+};
 use crate::lex::{Scanner, Token, TokenType, TokenType::*};
-use std::iter::Peekable;
+use std::iter::{Filter, Peekable};
+use std::path::Path;
+use std::rc::Rc;
+
+type TokenFilter<'a> = fn(&Token<'a>) -> bool;
 
 pub struct Parser<'a> {
-    tokens: Peekable<Scanner<'a>>,
+    tokens: Peekable<Filter<Scanner<'a>, TokenFilter<'a>>>,
+    // The `///` comments skipped since the last token was consumed.
+    docs: Vec<&'a str>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(scanner: Scanner<'a>) -> Self {
-        let tokens = scanner.peekable();
-        Self { tokens }
+        // Comments are kept by the scanner for tooling, but have no meaning here.
+        let not_comment: TokenFilter<'a> = |token| token.typ != Comment;
+        let tokens = scanner.filter(not_comment).peekable();
+        Self {
+            tokens,
+            docs: Vec::new(),
+        }
     }
 
     fn is_done(&mut self) -> bool {
-        match self.peek_type() {
-            None | Some(TokenType::Eof) => true,
-            _ => false,
-        }
+        matches!(self.peek_type(), None | Some(TokenType::Eof))
     }
 
     pub fn parse(&mut self) -> ParseResult<Vec<Stmt>> {
@@ -28,43 +39,83 @@ impl<'a> Parser<'a> {
     }
 
     pub fn declaration(&mut self) -> ParseResult<Stmt> {
-        if self.match_next(TokenType::Var) {
-            self.var_decl()
+        let line = self.peek_line();
+        if self.peek_type() == Some(Var) {
+            let doc = self.doc();
+            self.advance();
+            self.var_decl(line, doc)
+        } else if self.match_next(Import) {
+            self.import(line)
         } else {
             self.statement()
         }
     }
 
-    fn var_decl(&mut self) -> ParseResult<Stmt> {
-        let name = self.consume(TokenType::Identifier)?.lexeme.to_owned();
+    /// The doc comment on the declaration starting at the next token.
+    fn doc(&mut self) -> Option<String> {
+        if self.docs.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = self
+            .docs
+            .iter()
+            .map(|doc| {
+                let text = doc.trim_start_matches("///").trim_end();
+                text.strip_prefix(' ').unwrap_or(text)
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Parse the rest of a variable declaration, after the `var` on `line`.
+    fn var_decl(&mut self, line: usize, doc: Option<String>) -> ParseResult<Stmt> {
+        let name = Symbol::from(self.consume(TokenType::Identifier)?.name());
         let initializer = self
             .match_next(TokenType::Equal)
             .then(|| self.expression())
             .transpose()?;
         self.consume(TokenType::Semicolon)?;
-        Ok(Stmt::Var { name, initializer })
+        Ok(Stmt::new(
+            line,
+            StmtKind::Var {
+                name,
+                initializer,
+                doc,
+            },
+        ))
+    }
+
+    /// Parse the rest of an import, after the `import` on `line`.
+    fn import(&mut self, line: usize) -> ParseResult<Stmt> {
+        let token = self.consume(String_)?;
+        let path: Rc<str> = token.value.as_deref().unwrap_or_default().into();
+        let (name, alias) = if self.match_next(As) {
+            (Symbol::from(self.consume(Identifier)?.name()), true)
+        } else if let Some(name) = module_name(&path) {
+            (name, false)
+        } else {
+            let expected = "`as` and a name for the module";
+            let token = self.peek().ok_or(ParseError::end(expected))?;
+            return Err(ParseError::wrong_token(token, expected));
+        };
+        self.consume(Semicolon)?;
+        Ok(Stmt::new(line, StmtKind::Import { path, name, alias }))
     }
 
     pub fn statement(&mut self) -> ParseResult<Stmt> {
         use TokenType::*;
 
+        let line = self.peek_line();
         match self
-            .advance_any_of(&[Print, LeftBrace, If, While, For])
+            .advance_any_of(&[Print, LeftBrace, If, While, For, Throw, Try])
             .map(|t| t.typ)
         {
             Some(Print) => {
                 let value = self.expression()?;
                 self.consume(TokenType::Semicolon)?;
-                Ok(Stmt::Print(value))
-            }
-            Some(LeftBrace) => {
-                let mut statements = Vec::new();
-                while self.peek_type() != Some(TokenType::RightBrace) {
-                    statements.push(self.declaration()?);
-                }
-                self.consume(TokenType::RightBrace)?;
-                Ok(Stmt::Block(statements))
+                Ok(Stmt::print(line, value))
             }
+            Some(LeftBrace) => self.block(line),
             Some(If) => {
                 self.consume(TokenType::LeftParen)?;
                 let condition = self.expression()?;
@@ -77,11 +128,7 @@ impl<'a> Parser<'a> {
                     None
                 };
 
-                Ok(Stmt::If {
-                    condition,
-                    then_branch: Box::new(then_branch),
-                    else_branch: else_branch.map(Box::new),
-                })
+                Ok(Stmt::if_else(line, condition, then_branch, else_branch))
             }
             Some(While) => {
                 self.consume(TokenType::LeftParen)?;
@@ -89,18 +136,16 @@ impl<'a> Parser<'a> {
                 self.consume(TokenType::RightParen)?;
                 let body = self.statement()?;
 
-                Ok(Stmt::While {
-                    condition,
-                    body: Box::new(body),
-                })
+                Ok(Stmt::while_loop(line, condition, body))
             }
             Some(For) => {
                 self.consume(TokenType::LeftParen)?;
 
+                let init_line = self.peek_line();
                 let initializer: Option<Stmt> = if self.match_next(Semicolon) {
                     None
                 } else if self.match_next(Var) {
-                    Some(self.var_decl()?)
+                    Some(self.var_decl(init_line, None)?)
                 } else {
                     Some(self.expr_stmt()?)
                 };
@@ -113,40 +158,86 @@ impl<'a> Parser<'a> {
                     Some(expr)
                 };
 
-                let increment = if self.match_next(Semicolon) {
+                let increment = if self.match_next(RightParen) {
                     None
                 } else {
                     let expr = self.expression()?;
-                    self.consume(Semicolon)?;
+                    self.consume(RightParen)?;
                     Some(expr)
                 };
 
-                let mut body = self.statement()?;
-
-                if let Some(incr) = increment {
-                    body = Stmt::Block(vec![body, Stmt::Expression(incr)]);
-                }
+                let body = self.statement()?;
 
-                body = Stmt::While {
-                    condition: condition.unwrap_or(Expr::bool(true)),
-                    body: Box::new(body),
+                Ok(Stmt::new(
+                    line,
+                    StmtKind::For {
+                        initializer: initializer.map(Box::new),
+                        condition,
+                        increment,
+                        body: Box::new(body),
+                    },
+                ))
+            }
+            Some(Throw) => {
+                let value = self.expression()?;
+                self.consume(Semicolon)?;
+                Ok(Stmt::new(line, StmtKind::Throw(value)))
+            }
+            Some(Try) => {
+                let body = Box::new(self.braced_block()?);
+                let catch = if self.match_next(Catch) {
+                    self.consume(LeftParen)?;
+                    let name = Symbol::from(self.consume(Identifier)?.name());
+                    self.consume(RightParen)?;
+                    Some((name, Box::new(self.braced_block()?)))
+                } else {
+                    None
                 };
-
-                if let Some(init) = initializer {
-                    body = Stmt::Block(vec![init, body]);
+                let finally = if self.match_next(Finally) {
+                    Some(Box::new(self.braced_block()?))
+                } else {
+                    None
+                };
+                if catch.is_none() && finally.is_none() {
+                    let expected = "`catch` or `finally`";
+                    let token = self.peek().ok_or(ParseError::end(expected))?;
+                    return Err(ParseError::wrong_token(token, expected));
                 }
-
-                Ok(body)
+                Ok(Stmt::new(
+                    line,
+                    StmtKind::Try {
+                        body,
+                        catch,
+                        finally,
+                    },
+                ))
             }
             // Expression statement
             _ => self.expr_stmt(),
         }
     }
 
+    /// Parse the rest of a block after its `{` on `line`.
+    fn block(&mut self, line: usize) -> ParseResult<Stmt> {
+        let mut statements = Vec::new();
+        while self.peek_type() != Some(TokenType::RightBrace) && !self.is_done() {
+            statements.push(self.declaration()?);
+        }
+        self.consume(TokenType::RightBrace)?;
+        Ok(Stmt::block(line, statements))
+    }
+
+    fn braced_block(&mut self) -> ParseResult<Stmt> {
+        let line = self.peek_line();
+        self.consume(TokenType::LeftBrace)?;
+        self.block(line)
+    }
+
     fn expr_stmt(&mut self) -> ParseResult<Stmt> {
+        let line = self.peek_line();
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon)?;
-        Ok(Stmt::Expression(expr))
+        Ok(Stmt::expression(line, expr))
     }
 
     pub fn expression(&mut self) -> ParseResult<Expr> {
@@ -154,19 +245,38 @@ impl<'a> Parser<'a> {
     }
 
     fn assignment(&mut self) -> ParseResult<Expr> {
-        let expr = self.or()?;
+        let expr = self.conditional()?;
+
+        let op = match self.peek_type() {
+            Some(Equal) => None,
+            Some(PlusEqual) => Some(BinaryOp::Add),
+            Some(MinusEqual) => Some(BinaryOp::Sub),
+            Some(StarEqual) => Some(BinaryOp::Mult),
+            Some(SlashEqual) => Some(BinaryOp::Div),
+            _ => return Ok(expr),
+        };
+        let line = self.peek_line();
+        self.advance();
+        let value = self.assignment()?;
+        let target = LValue::from_expr(expr).ok_or(ParseError::InvalidAssignment { line })?;
+        Ok(Expr::Assign {
+            target,
+            op,
+            expr: Box::new(value),
+        })
+    }
 
-        if let Some(token) = self.advance_only(Equal) {
-            let line = token.line;
-            let value = self.assignment()?;
-            return if let Expr::Variable(name) = expr {
-                Ok(Expr::assign(name, value))
-            } else {
-                Err(ParseError::InvalidAssignment { line })
-            };
+    fn conditional(&mut self) -> ParseResult<Expr> {
+        let condition = self.or()?;
+
+        if self.match_next(Question) {
+            let then_branch = self.expression()?;
+            self.consume(Colon)?;
+            let else_branch = self.conditional()?;
+            return Ok(Expr::conditional(condition, then_branch, else_branch));
         }
 
-        Ok(expr)
+        Ok(condition)
     }
 
     fn or(&mut self) -> ParseResult<Expr> {
@@ -204,53 +314,101 @@ impl<'a> Parser<'a> {
     }
 
     fn comparison(&mut self) -> ParseResult<Expr> {
-        let left = self.term()?;
+        let left = self.bit_or()?;
 
         if self.match_next(Greater) {
-            return Ok(Expr::binary(left, BinaryOp::Greater, self.term()?));
+            return Ok(Expr::binary(left, BinaryOp::Greater, self.bit_or()?));
         }
 
         if self.match_next(GreaterEqual) {
-            return Ok(Expr::binary(left, BinaryOp::GreaterEqual, self.term()?));
+            return Ok(Expr::binary(left, BinaryOp::GreaterEqual, self.bit_or()?));
         }
 
         if self.match_next(Less) {
-            return Ok(Expr::binary(left, BinaryOp::Less, self.term()?));
+            return Ok(Expr::binary(left, BinaryOp::Less, self.bit_or()?));
         }
 
         if self.match_next(LessEqual) {
-            return Ok(Expr::binary(left, BinaryOp::LessEqual, self.term()?));
+            return Ok(Expr::binary(left, BinaryOp::LessEqual, self.bit_or()?));
         }
 
         Ok(left)
     }
 
-    fn term(&mut self) -> ParseResult<Expr> {
-        let left = self.factor()?;
+    fn bit_or(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.bit_xor()?;
 
-        if self.match_next(Minus) {
-            return Ok(Expr::binary(left, BinaryOp::Sub, self.term()?));
+        while self.match_next(Pipe) {
+            expr = Expr::binary(expr, BinaryOp::BitOr, self.bit_xor()?);
         }
 
-        if self.match_next(Plus) {
-            return Ok(Expr::binary(left, BinaryOp::Add, self.term()?));
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.bit_and()?;
+
+        while self.match_next(Caret) {
+            expr = Expr::binary(expr, BinaryOp::BitXor, self.bit_and()?);
         }
 
-        Ok(left)
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> ParseResult<Expr> {
-        let left = self.unary()?;
+    fn bit_and(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.shift()?;
 
-        if self.match_next(Slash) {
-            return Ok(Expr::binary(left, BinaryOp::Div, self.factor()?));
+        while self.match_next(Ampersand) {
+            expr = Expr::binary(expr, BinaryOp::BitAnd, self.shift()?);
         }
 
-        if self.match_next(Star) {
-            return Ok(Expr::binary(left, BinaryOp::Mult, self.factor()?));
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.term()?;
+
+        loop {
+            if self.match_next(LessLess) {
+                expr = Expr::binary(expr, BinaryOp::ShiftLeft, self.term()?);
+            } else if self.match_next(GreaterGreater) {
+                expr = Expr::binary(expr, BinaryOp::ShiftRight, self.term()?);
+            } else {
+                return Ok(expr);
+            }
         }
+    }
 
-        Ok(left)
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.factor()?;
+
+        loop {
+            if self.match_next(Minus) {
+                expr = Expr::binary(expr, BinaryOp::Sub, self.factor()?);
+            } else if self.match_next(Plus) {
+                expr = Expr::binary(expr, BinaryOp::Add, self.factor()?);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.unary()?;
+
+        loop {
+            if self.match_next(Slash) {
+                expr = Expr::binary(expr, BinaryOp::Div, self.unary()?);
+            } else if self.match_next(Star) {
+                expr = Expr::binary(expr, BinaryOp::Mult, self.unary()?);
+            } else if self.match_next(Percent) {
+                expr = Expr::binary(expr, BinaryOp::Mod, self.unary()?);
+            } else if self.match_next(TildeSlash) {
+                expr = Expr::binary(expr, BinaryOp::FloorDiv, self.unary()?);
+            } else {
+                return Ok(expr);
+            }
+        }
     }
 
     fn unary(&mut self) -> ParseResult<Expr> {
@@ -260,11 +418,76 @@ impl<'a> Parser<'a> {
         if self.match_next(Minus) {
             return Ok(Expr::unary(UnaryOp::Minus, self.unary()?));
         }
-        self.primary()
+        if self.match_next(Tilde) {
+            return Ok(Expr::unary(UnaryOp::BitNot, self.unary()?));
+        }
+        self.power()
+    }
+
+    /// `**` binds tighter than a prefix operator on its left, so `-2 ** 2` is
+    /// `-(2 ** 2)`, and groups to the right.
+    fn power(&mut self) -> ParseResult<Expr> {
+        let base = self.update()?;
+
+        if self.match_next(StarStar) {
+            return Ok(Expr::binary(base, BinaryOp::Pow, self.unary()?));
+        }
+
+        Ok(base)
+    }
+
+    /// `++` and `--`, before or after their target.
+    fn update(&mut self) -> ParseResult<Expr> {
+        let line = self.peek_line();
+        if let Some(op) = self.update_op() {
+            let target = self.update()?;
+            let target = LValue::from_expr(target).ok_or(ParseError::InvalidAssignment { line })?;
+            return Ok(Expr::update(target, op, true));
+        }
+
+        let expr = self.access()?;
+        let line = self.peek_line();
+        if let Some(op) = self.update_op() {
+            let target = LValue::from_expr(expr).ok_or(ParseError::InvalidAssignment { line })?;
+            return Ok(Expr::update(target, op, false));
+        }
+
+        Ok(expr)
+    }
+
+    /// Property accesses: `object.name`.
+    fn access(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+
+        while self.match_next(Dot) {
+            let name = Symbol::from(self.consume(Identifier)?.name());
+            expr = Expr::get(expr, name);
+        }
+
+        Ok(expr)
+    }
+
+    fn update_op(&mut self) -> Option<UpdateOp> {
+        if self.match_next(PlusPlus) {
+            Some(UpdateOp::Increment)
+        } else if self.match_next(MinusMinus) {
+            Some(UpdateOp::Decrement)
+        } else {
+            None
+        }
     }
 
     fn primary(&mut self) -> ParseResult<Expr> {
-        let primary_types = [Nil, False, True, Number, String_, Identifier, LeftParen];
+        let primary_types = [
+            Nil,
+            False,
+            True,
+            Number,
+            String_,
+            Interpolation,
+            Identifier,
+            LeftParen,
+        ];
 
         let token = self.advance_expect("primary expression", |token| {
             primary_types.contains(&token.typ)
@@ -273,30 +496,62 @@ impl<'a> Parser<'a> {
             Nil => Ok(Expr::nil()),
             False => Ok(Expr::bool(false)),
             True => Ok(Expr::bool(true)),
-            Number => {
-                let num: f64 = token.lexeme.parse().unwrap();
-                Ok(Expr::number(num))
-            }
-            String_ => Ok(Expr::string(token.lexeme)),
+            Number => match number_value(token.lexeme) {
+                Some(num) => Ok(Expr::number(num)),
+                None => Err(ParseError::InvalidNumber {
+                    line: token.line,
+                    lexeme: token.lexeme.to_owned(),
+                }),
+            },
+            String_ => Ok(Expr::string(token.value.as_deref().unwrap_or_default())),
+            Interpolation => self.interpolation(token),
             LeftParen => {
                 let expr = self.expression()?;
                 self.consume(RightParen)?;
                 Ok(Expr::group(expr))
             }
-            Identifier => Ok(Expr::var(token.lexeme)),
+            Identifier => Ok(Expr::var(token.name())),
             _ => Err(ParseError::wrong_token(&token, "expression")),
         }
     }
 
-    fn advance(&mut self) -> Option<Token> {
+    /// Parse the rest of an interpolated string after its first part.
+    fn interpolation(&mut self, first: Token<'a>) -> ParseResult<Expr> {
+        let mut parts = Vec::new();
+        let mut token = first;
+        loop {
+            let text = token.value.as_deref().unwrap_or_default();
+            if !text.is_empty() {
+                parts.push(Expr::string(text));
+            }
+            if token.typ == String_ {
+                return Ok(Expr::Interpolation(parts));
+            }
+            parts.push(self.expression()?);
+            token = self.advance_expect("`}` and the rest of the string", |t| {
+                matches!(t.typ, Interpolation | String_)
+            })?;
+        }
+    }
+
+    fn advance(&mut self) -> Option<Token<'a>> {
+        self.skip_docs();
+        self.docs.clear();
         self.tokens.next()
     }
 
+    /// Set aside doc comments, which can only be read by `doc`.
+    fn skip_docs(&mut self) {
+        while let Some(doc) = self.tokens.next_if(|t| t.typ == DocComment) {
+            self.docs.push(doc.lexeme);
+        }
+    }
+
     fn advance_expect(
         &mut self,
         message: &str,
         pred: impl FnOnce(&Token<'a>) -> bool,
-    ) -> ParseResult<Token> {
+    ) -> ParseResult<Token<'a>> {
         let token = self.peek().ok_or(ParseError::end(message))?;
         if pred(token) {
             Ok(self.advance().unwrap())
@@ -305,7 +560,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn advance_any_of(&mut self, typs: &[TokenType]) -> Option<Token> {
+    fn advance_any_of(&mut self, typs: &[TokenType]) -> Option<Token<'a>> {
         if typs.contains(&self.peek()?.typ) {
             self.advance()
         } else {
@@ -314,7 +569,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Advance only if the next token is of the given type
-    fn advance_only(&mut self, typ: TokenType) -> Option<Token> {
+    fn advance_only(&mut self, typ: TokenType) -> Option<Token<'a>> {
         if self.peek()?.typ == typ {
             self.advance()
         } else {
@@ -327,22 +582,67 @@ impl<'a> Parser<'a> {
     }
 
     fn peek(&mut self) -> Option<&Token<'a>> {
+        self.skip_docs();
         self.tokens.peek()
     }
 
+    /// The line of the next token, or 0 if there are no tokens left.
+    fn peek_line(&mut self) -> usize {
+        self.peek().map_or(0, |t| t.line)
+    }
+
     fn peek_type(&mut self) -> Option<TokenType> {
-        self.tokens.peek().map(|t| t.typ)
+        self.peek().map(|t| t.typ)
     }
 
-    fn consume(&mut self, typ: TokenType) -> ParseResult<Token> {
+    fn consume(&mut self, typ: TokenType) -> ParseResult<Token<'a>> {
         self.advance_expect(&format!("token of type {typ:?}"), |t| t.typ == typ)
     }
 }
 
+/// The value of a number literal as scanned by `Scanner`, or `None` if it
+/// isn't one.  Like decimal literals, hex and binary literals too big for a
+/// float overflow to infinity.
+fn number_value(lexeme: &str) -> Option<f64> {
+    let digits = lexeme.replace('_', "");
+    if !digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+        // Rust also accepts eg `inf` and `+1`, which Lox doesn't.
+        return None;
+    }
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x" | "0X") => (16, &digits[2..]),
+        Some("0b" | "0B") => (2, &digits[2..]),
+        _ => return digits.parse().ok(),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(0., |acc, ch| {
+        let digit = ch.to_digit(radix)?;
+        Some(acc * f64::from(radix) + f64::from(digit))
+    })
+}
+
+/// The name an import without `as` binds: the file name of `path` without
+/// its extension, if that is an identifier.
+fn module_name(path: &str) -> Option<Symbol> {
+    let stem = Path::new(path).file_stem()?.to_str()?;
+    let mut tokens = Scanner::new(stem);
+    match (tokens.next(), tokens.next()) {
+        (Some(token), Some(Token { typ: Eof, .. }))
+            if token.typ == Identifier && token.lexeme == stem =>
+        {
+            Some(Symbol::from(token.name()))
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lex::Scanner;
+    use crate::parse::sexpr;
 
     fn assert_parse_expr(source: &str, expected: Result<Expr, ParseError>) {
         let scanner = Scanner::new(source);
@@ -370,7 +670,22 @@ mod tests {
 
     #[test]
     fn test_parse_number() {
-        assert_parse_expr("1", Ok(Expr::number(1.0)))
+        assert_parse_expr("1", Ok(Expr::number(1.0)));
+        assert_parse_expr("1_000.5e-1", Ok(Expr::number(100.05)));
+        assert_parse_expr("0xff_FF", Ok(Expr::number(65535.)));
+        assert_parse_expr("0b1010", Ok(Expr::number(10.)));
+        assert_parse_expr("1e400", Ok(Expr::number(f64::INFINITY)));
+        let wide = format!("0x{}", "f".repeat(33));
+        assert_parse_expr(&wide, Ok(Expr::number(16f64.powi(33))));
+        let huge = format!("0b1{}", "0".repeat(1024));
+        assert_parse_expr(&huge, Ok(Expr::number(f64::INFINITY)));
+    }
+
+    #[test]
+    fn test_parse_number_error() {
+        assert_eq!(number_value("inf"), None);
+        assert_eq!(number_value("0b12"), None);
+        assert_eq!(number_value("0x"), None);
     }
 
     #[test]
@@ -456,6 +771,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_arithmetic_and_bitwise_precedence() {
+        let parsed = |source| sexpr::expr(&Parser::new(Scanner::new(source)).expression().unwrap());
+        assert_eq!(parsed("-2 ** 2"), "(- (** 2 2))");
+        assert_eq!(parsed("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+        assert_eq!(parsed("2 ** -1"), "(** 2 (- 1))");
+        assert_eq!(parsed("a % 2 ~/ 3 * 4"), "(* (~/ (% a 2) 3) 4)");
+        assert_eq!(parsed("1 << 2 + 3 >> 4"), "(>> (<< 1 (+ 2 3)) 4)");
+        assert_eq!(parsed("a | b ^ c & d << 1"), "(| a (^ b (& c (<< d 1))))");
+        assert_eq!(parsed("a & 1 == 0"), "(== (& a 1) 0)");
+        assert_eq!(parsed("~a & ~-b"), "(& (~ a) (~ (- b)))");
+    }
+
     #[test]
     fn test_parse_grouping() {
         assert_parse_expr(
@@ -478,12 +806,38 @@ mod tests {
         assert_parse_expr("\"a\nb\"", Ok(Expr::string("a\nb")));
     }
 
+    #[test]
+    fn test_parse_interpolation() {
+        assert_parse_expr(
+            r#""a${b}${1 + 2}c""#,
+            Ok(Expr::Interpolation(vec![
+                Expr::string("a"),
+                Expr::var("b"),
+                Expr::binary(Expr::number(1.), BinaryOp::Add, Expr::number(2.)),
+                Expr::string("c"),
+            ])),
+        );
+        assert_parse_expr(
+            r#""${"${x}"}""#,
+            Ok(Expr::Interpolation(vec![Expr::Interpolation(vec![
+                Expr::var("x"),
+            ])])),
+        );
+        assert_parse_expr(
+            r#""a${b c}""#,
+            Err(ParseError::wrong_token(
+                &Token::new(1, 7, TokenType::Identifier, "c"),
+                "`}` and the rest of the string",
+            )),
+        );
+    }
+
     #[test]
     fn test_parse_error_eof() {
         assert_parse_expr(
             "(",
             Err(ParseError::wrong_token(
-                &Token::new(1, TokenType::Eof, ""),
+                &Token::new(1, 2, TokenType::Eof, ""),
                 "primary expression",
             )),
         );
@@ -491,7 +845,7 @@ mod tests {
         assert_parse_expr(
             "(1 2",
             Err(ParseError::wrong_token(
-                &Token::new(1, TokenType::Number, "2"),
+                &Token::new(1, 4, TokenType::Number, "2"),
                 "token of type RightParen",
             )),
         );
@@ -546,44 +900,62 @@ mod tests {
         assert_parse_expr(
             r#"1 + 2 + 3"#,
             Ok(Expr::binary(
-                Expr::number(1.0),
+                Expr::binary(Expr::number(1.0), BinaryOp::Add, Expr::number(2.0)),
                 BinaryOp::Add,
-                Expr::binary(Expr::number(2.0), BinaryOp::Add, Expr::number(3.0)),
+                Expr::number(3.0),
             )),
         );
 
         assert_parse_expr(
             r#"1 * 2 * 3"#,
             Ok(Expr::binary(
-                Expr::number(1.0),
+                Expr::binary(Expr::number(1.0), BinaryOp::Mult, Expr::number(2.0)),
                 BinaryOp::Mult,
-                Expr::binary(Expr::number(2.0), BinaryOp::Mult, Expr::number(3.0)),
+                Expr::number(3.0),
             )),
         );
 
         assert_parse_expr(
             r#"1 + 2 * 3 + 4"#,
             Ok(Expr::binary(
-                Expr::number(1.0),
-                BinaryOp::Add,
                 Expr::binary(
-                    Expr::binary(Expr::number(2.0), BinaryOp::Mult, Expr::number(3.0)),
+                    Expr::number(1.0),
                     BinaryOp::Add,
-                    Expr::number(4.0),
+                    Expr::binary(Expr::number(2.0), BinaryOp::Mult, Expr::number(3.0)),
                 ),
+                BinaryOp::Add,
+                Expr::number(4.0),
             )),
         );
 
         assert_parse_expr(
             r#"1 + 2 + 3 + 4"#,
             Ok(Expr::binary(
-                Expr::number(1.0),
-                BinaryOp::Add,
                 Expr::binary(
-                    Expr::number(2.0),
+                    Expr::binary(Expr::number(1.0), BinaryOp::Add, Expr::number(2.0)),
                     BinaryOp::Add,
-                    Expr::binary(Expr::number(3.0), BinaryOp::Add, Expr::number(4.0)),
+                    Expr::number(3.0),
                 ),
+                BinaryOp::Add,
+                Expr::number(4.0),
+            )),
+        );
+
+        assert_parse_expr(
+            r#"8 - 4 - 2"#,
+            Ok(Expr::binary(
+                Expr::binary(Expr::number(8.0), BinaryOp::Sub, Expr::number(4.0)),
+                BinaryOp::Sub,
+                Expr::number(2.0),
+            )),
+        );
+
+        assert_parse_expr(
+            r#"8 / 4 / 2"#,
+            Ok(Expr::binary(
+                Expr::binary(Expr::number(8.0), BinaryOp::Div, Expr::number(4.0)),
+                BinaryOp::Div,
+                Expr::number(2.0),
             )),
         );
     }
@@ -593,9 +965,9 @@ mod tests {
         assert_parse_expr(
             r#""Hi, " + first + "!""#,
             Ok(Expr::binary(
-                Expr::string("Hi, "),
+                Expr::binary(Expr::string("Hi, "), BinaryOp::Add, Expr::var("first")),
                 BinaryOp::Add,
-                Expr::binary(Expr::var("first"), BinaryOp::Add, Expr::string("!")),
+                Expr::string("!"),
             )),
         );
     }
@@ -630,9 +1002,61 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_compound_assignment() {
+        assert_parse_expr(
+            "a += b -= 2",
+            Ok(Expr::compound_assign(
+                LValue::var("a"),
+                BinaryOp::Add,
+                Expr::compound_assign(LValue::var("b"), BinaryOp::Sub, Expr::number(2.)),
+            )),
+        );
+        assert_parse_expr(
+            "a *= 1 /= 2",
+            Err(ParseError::InvalidAssignment { line: 1 }),
+        );
+    }
+
+    #[test]
+    fn test_parse_update() {
+        let parsed = |source| sexpr::expr(&Parser::new(Scanner::new(source)).expression().unwrap());
+        assert_eq!(parsed("a++"), "(post++ a)");
+        assert_eq!(parsed("--a"), "(-- a)");
+        assert_eq!(parsed("-a--"), "(- (post-- a))");
+        assert_eq!(parsed("++a ** 2"), "(** (++ a) 2)");
+        assert_eq!(parsed("a++ + ++b"), "(+ (post++ a) (++ b))");
+        for source in ["1++", "++a++", "--(a)"] {
+            assert_parse_expr(source, Err(ParseError::InvalidAssignment { line: 1 }));
+        }
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        assert_parse_expr(
+            "a or b ? c = 1 : d ? 2 : 3",
+            Ok(Expr::conditional(
+                Expr::logical(Expr::var("a"), LogicalOp::Or, Expr::var("b")),
+                Expr::assign("c", Expr::number(1.)),
+                Expr::conditional(Expr::var("d"), Expr::number(2.), Expr::number(3.)),
+            )),
+        );
+        assert_parse_expr(
+            "x = a ? b : c",
+            Ok(Expr::assign(
+                "x",
+                Expr::conditional(Expr::var("a"), Expr::var("b"), Expr::var("c")),
+            )),
+        );
+        assert_parse_expr(
+            "a ? b : c = d",
+            Err(ParseError::InvalidAssignment { line: 1 }),
+        );
+    }
+
     #[test]
     fn test_parse_stmt_expr() {
-        assert_parse_stmt("1;", Ok(Stmt::Expression(Expr::number(1.))));
+        assert_parse_stmt("1;", Ok(Stmt::expression(1, Expr::number(1.))));
         assert_parse_stmt(
             "1",
             Err(ParseError::UnexpectedToken {
@@ -646,7 +1070,7 @@ mod tests {
 
     #[test]
     fn test_parse_stmt_print() {
-        assert_parse_stmt("print 1;", Ok(Stmt::Print(Expr::number(1.))));
+        assert_parse_stmt("print 1;", Ok(Stmt::print(1, Expr::number(1.))));
         assert_parse_stmt(
             "print 1",
             Err(ParseError::UnexpectedToken {
@@ -669,13 +1093,7 @@ mod tests {
 
     #[test]
     fn test_parse_var_decl() {
-        assert_parse_stmt(
-            "var a = 1;",
-            Ok(Stmt::Var {
-                name: "a".to_owned(),
-                initializer: Some(Expr::number(1.0)),
-            }),
-        );
+        assert_parse_stmt("var a = 1;", Ok(Stmt::var(1, "a", Some(Expr::number(1.0)))));
         assert_parse_stmt(
             "var = 1;",
             Err(ParseError::UnexpectedToken {
@@ -689,7 +1107,7 @@ mod tests {
 
     #[test]
     fn test_parse_var_use() {
-        assert_parse_stmt("print a;", Ok(Stmt::Print(Expr::var("a"))));
+        assert_parse_stmt("print a;", Ok(Stmt::print(1, Expr::var("a"))));
         assert_parse_stmt(
             "print a",
             Err(ParseError::UnexpectedToken {
@@ -711,10 +1129,13 @@ mod tests {
     fn test_parse_block() {
         assert_parse_stmt(
             "{ 1; 2;}",
-            Ok(Stmt::Block(vec![
-                Stmt::Expression(Expr::number(1.)),
-                Stmt::Expression(Expr::number(2.)),
-            ])),
+            Ok(Stmt::block(
+                1,
+                vec![
+                    Stmt::expression(1, Expr::number(1.)),
+                    Stmt::expression(1, Expr::number(2.)),
+                ],
+            )),
         );
     }
 
@@ -722,10 +1143,13 @@ mod tests {
     fn test_parse_nested_block() {
         assert_parse_stmt(
             "{ 1; { 2; } }",
-            Ok(Stmt::Block(vec![
-                Stmt::Expression(Expr::number(1.)),
-                Stmt::Block(vec![Stmt::Expression(Expr::number(2.))]),
-            ])),
+            Ok(Stmt::block(
+                1,
+                vec![
+                    Stmt::expression(1, Expr::number(1.)),
+                    Stmt::block(1, vec![Stmt::expression(1, Expr::number(2.))]),
+                ],
+            )),
         );
     }
 
@@ -733,11 +1157,12 @@ mod tests {
     fn test_if_stmt() {
         assert_parse_stmt(
             "if (true) 1;",
-            Ok(Stmt::If {
-                condition: Expr::bool(true),
-                then_branch: Box::new(Stmt::Expression(Expr::number(1.))),
-                else_branch: None,
-            }),
+            Ok(Stmt::if_else(
+                1,
+                Expr::bool(true),
+                Stmt::expression(1, Expr::number(1.)),
+                None,
+            )),
         );
         assert_parse_stmt(
             "if true 1;",
@@ -754,11 +1179,12 @@ mod tests {
     fn test_if_else_stmt() {
         assert_parse_stmt(
             "if (true) 1; else 2;",
-            Ok(Stmt::If {
-                condition: Expr::bool(true),
-                then_branch: Box::new(Stmt::Expression(Expr::number(1.))),
-                else_branch: Some(Box::new(Stmt::Expression(Expr::number(2.)))),
-            }),
+            Ok(Stmt::if_else(
+                1,
+                Expr::bool(true),
+                Stmt::expression(1, Expr::number(1.)),
+                Some(Stmt::expression(1, Expr::number(2.))),
+            )),
         );
         assert_parse_stmt(
             "if (true) 1; else 2",
@@ -771,15 +1197,118 @@ mod tests {
         );
         assert_parse_stmt(
             "if (first) if (second) 1; else 2;",
-            Ok(Stmt::If {
-                condition: Expr::var("first"),
-                then_branch: Box::new(Stmt::If {
-                    condition: Expr::var("second"),
-                    then_branch: Box::new(Stmt::Expression(Expr::number(1.))),
-                    else_branch: Some(Box::new(Stmt::Expression(Expr::number(2.)))),
-                }),
-                else_branch: None,
-            }),
+            Ok(Stmt::if_else(
+                1,
+                Expr::var("first"),
+                Stmt::if_else(
+                    1,
+                    Expr::var("second"),
+                    Stmt::expression(1, Expr::number(1.)),
+                    Some(Stmt::expression(1, Expr::number(2.))),
+                ),
+                None,
+            )),
         )
     }
+
+    #[test]
+    fn test_parse_skips_comments() {
+        assert_parse_stmt(
+            "// leading\nprint 1; // trailing",
+            Ok(Stmt::print(2, Expr::number(1.))),
+        );
+    }
+
+    #[test]
+    fn test_parse_doc_comments() {
+        assert_parse_stmt(
+            "/// The answer.\n///   Indented.\nvar a = /// ignored\n 42;",
+            Ok(Stmt::new(
+                3,
+                StmtKind::Var {
+                    name: "a".into(),
+                    initializer: Some(Expr::number(42.)),
+                    doc: Some("The answer.\n  Indented.".to_owned()),
+                },
+            )),
+        );
+        // Docs only attach to the declaration straight after them.
+        assert_parse_stmt(
+            "/// Not a declaration.\nprint 1;",
+            Ok(Stmt::print(2, Expr::number(1.))),
+        );
+    }
+
+    #[test]
+    fn test_parse_stmt_lines() {
+        assert_parse_stmt(
+            "{\n  var a =\n    1;\n  print a;\n}",
+            Ok(Stmt::block(
+                1,
+                vec![
+                    Stmt::var(2, "a", Some(Expr::number(1.))),
+                    Stmt::print(4, Expr::var("a")),
+                ],
+            )),
+        );
+    }
+
+    #[test]
+    fn test_parse_for_stmt() {
+        assert_parse_stmt(
+            "for (var i = 0; i < 2; i = i + 1) print i;",
+            Ok(Stmt::new(
+                1,
+                StmtKind::For {
+                    initializer: Some(Box::new(Stmt::var(1, "i", Some(Expr::number(0.))))),
+                    condition: Some(Expr::binary(
+                        Expr::var("i"),
+                        BinaryOp::Less,
+                        Expr::number(2.),
+                    )),
+                    increment: Some(Expr::assign(
+                        "i",
+                        Expr::binary(Expr::var("i"), BinaryOp::Add, Expr::number(1.)),
+                    )),
+                    body: Box::new(Stmt::print(1, Expr::var("i"))),
+                },
+            )),
+        );
+        assert_parse_stmt(
+            "for (;;) 1;",
+            Ok(Stmt::new(
+                1,
+                StmtKind::For {
+                    initializer: None,
+                    condition: None,
+                    increment: None,
+                    body: Box::new(Stmt::expression(1, Expr::number(1.))),
+                },
+            )),
+        );
+    }
+
+    #[test]
+    fn test_parse_try_stmt() {
+        assert_parse_stmt(
+            "try {\n  throw e.line;\n} catch (e) {} finally {}",
+            Ok(Stmt::new(
+                1,
+                StmtKind::Try {
+                    body: Box::new(Stmt::block(
+                        1,
+                        vec![Stmt::new(
+                            2,
+                            StmtKind::Throw(Expr::get(Expr::var("e"), "line")),
+                        )],
+                    )),
+                    catch: Some((Symbol::intern("e"), Box::new(Stmt::block(3, vec![])))),
+                    finally: Some(Box::new(Stmt::block(3, vec![]))),
+                },
+            )),
+        );
+        for source in ["try {}", "try 1; finally {}", "try {} catch e {}", "e.1;"] {
+            assert!(Parser::new(Scanner::new(source)).declaration().is_err());
+        }
+    }
 }
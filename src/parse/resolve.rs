@@ -0,0 +1,175 @@
+//! Works out where each variable is stored before the program runs, so the
+//! interpreter can index straight into its stack of locals.
+//!
+//! Runs on the lowered AST, which is the only AST the interpreter runs.
+use super::visit::{walk_expr_mut, walk_stmt_mut, VisitorMut};
+use super::{Expr, LValue, Slot, Stmt, StmtKind, Symbol};
+
+pub fn resolve(mut stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut resolver = Resolver::default();
+    for stmt in &mut stmts {
+        resolver.visit_stmt_mut(stmt);
+    }
+    stmts
+}
+
+#[derive(Default)]
+struct Resolver {
+    // Names declared in each enclosing block so far, innermost last.  A
+    // name's position is its index in the block's slots.
+    scopes: Vec<Vec<Symbol>>,
+}
+
+impl Resolver {
+    fn slot(&self, name: &Symbol) -> Slot {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                let index = scope.iter().position(|n| n == name)?;
+                Some(Slot::Local { depth, index })
+            })
+            .unwrap_or(Slot::Global)
+    }
+
+    fn declare(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.contains(&name) {
+                scope.push(name);
+            }
+        }
+    }
+}
+
+impl VisitorMut for Resolver {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Var {
+                name, initializer, ..
+            } => {
+                // The initializer can't see the variable it initializes.
+                if let Some(init) = initializer {
+                    self.visit_expr_mut(init);
+                }
+                self.declare(name.clone());
+            }
+            StmtKind::Import { name, .. } => self.declare(name.clone()),
+            StmtKind::Block(_) => {
+                self.scopes.push(Vec::new());
+                walk_stmt_mut(self, stmt);
+                self.scopes.pop();
+            }
+            // Lowering adds blocks that aren't in the tree yet.
+            StmtKind::For { .. } => (),
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_stmt_mut(body);
+                // The caught value is alone in a scope around the handler.
+                if let Some((name, handler)) = catch {
+                    self.scopes.push(vec![name.clone()]);
+                    self.visit_stmt_mut(handler);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.visit_stmt_mut(finally);
+                }
+            }
+            _ => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable { name, slot } => *slot = self.slot(name),
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_lvalue_mut(&mut self, target: &mut LValue) {
+        match target {
+            LValue::Variable { name, slot } => *slot = self.slot(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Scanner;
+    use crate::parse::{lower::lower, Parser};
+
+    // The slots of the variables read by `print` statements.
+    fn printed_slots(source: &str) -> Vec<Slot> {
+        struct Printed(Vec<Slot>);
+        impl VisitorMut for Printed {
+            fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+                if let StmtKind::Print(Expr::Variable { slot, .. }) = &stmt.kind {
+                    self.0.push(*slot);
+                }
+                walk_stmt_mut(self, stmt);
+            }
+        }
+        let stmts = lower(Parser::new(Scanner::new(source)).parse().unwrap());
+        let mut printed = Printed(Vec::new());
+        for stmt in &mut resolve(stmts) {
+            printed.visit_stmt_mut(stmt);
+        }
+        printed.0
+    }
+
+    #[test]
+    fn test_slots() {
+        let source = "var g; { var a; var b; print b; { var a = a; print a; print b; print g; } }";
+        assert_eq!(
+            printed_slots(source),
+            vec![
+                Slot::Local { depth: 0, index: 1 },
+                Slot::Local { depth: 0, index: 0 },
+                Slot::Local { depth: 1, index: 1 },
+                Slot::Global,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_later() {
+        // `a` isn't declared in the block until after the first print.
+        let source = "var a; { print a; var a; print a; var a; print a; }";
+        assert_eq!(
+            printed_slots(source),
+            vec![
+                Slot::Global,
+                Slot::Local { depth: 0, index: 0 },
+                Slot::Local { depth: 0, index: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lowered_for() {
+        let source = "for (var i = 0; i < 1; i = i + 1) { var j; print i; print j; }";
+        assert_eq!(
+            printed_slots(source),
+            vec![
+                Slot::Local { depth: 2, index: 0 },
+                Slot::Local { depth: 0, index: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_catch() {
+        let source = "try {} catch (e) { print e; { print e; } }";
+        assert_eq!(
+            printed_slots(source),
+            vec![
+                Slot::Local { depth: 1, index: 0 },
+                Slot::Local { depth: 2, index: 0 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Prints syntax trees as Lisp-style S-expressions, eg `(+ 1 (group (* 2 3)))`.
+use super::{Expr, LValue, Literal, Stmt, StmtKind};
+
+/// The statements of a program, one per line.
+pub fn program(stmts: &[Stmt]) -> String {
+    stmts.iter().map(|s| stmt(s) + "\n").collect()
+}
+
+pub fn stmt(stmt: &Stmt) -> String {
+    match &stmt.kind {
+        StmtKind::Expression(e) => format!("(; {})", expr(e)),
+        StmtKind::Print(e) => format!("(print {})", expr(e)),
+        StmtKind::Var {
+            name,
+            initializer: Some(init),
+            ..
+        } => format!("(var {name} {})", expr(init)),
+        StmtKind::Var {
+            name,
+            initializer: None,
+            ..
+        } => format!("(var {name})"),
+        StmtKind::Block(stmts) => parenthesize("block", stmts.iter().map(self::stmt)),
+        StmtKind::Import {
+            path,
+            name,
+            alias: true,
+        } => format!("(import {path:?} as {name})"),
+        StmtKind::Import { path, .. } => format!("(import {path:?})"),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let parts = [expr(condition), self::stmt(then_branch)];
+            let else_part = else_branch.as_deref().map(self::stmt);
+            parenthesize("if", parts.into_iter().chain(else_part))
+        }
+        StmtKind::While { condition, body } => {
+            format!("(while {} {})", expr(condition), self::stmt(body))
+        }
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => {
+            // Missing clauses print as `()` so the positions stay fixed.
+            let parts = [
+                initializer.as_deref().map_or("()".to_owned(), self::stmt),
+                condition.as_ref().map_or("()".to_owned(), expr),
+                increment.as_ref().map_or("()".to_owned(), expr),
+                self::stmt(body),
+            ];
+            parenthesize("for", parts.into_iter())
+        }
+        StmtKind::Throw(e) => format!("(throw {})", expr(e)),
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            let catch = catch
+                .iter()
+                .map(|(name, handler)| format!("(catch {name} {})", self::stmt(handler)));
+            let finally = finally
+                .iter()
+                .map(|stmt| format!("(finally {})", self::stmt(stmt)));
+            parenthesize(
+                "try",
+                [self::stmt(body)].into_iter().chain(catch).chain(finally),
+            )
+        }
+    }
+}
+
+pub fn expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(Literal::Nil) => "nil".to_owned(),
+        Expr::Literal(Literal::Bool(b)) => b.to_string(),
+        Expr::Literal(Literal::Number(n)) => n.to_string(),
+        Expr::Literal(Literal::String(s)) => format!("{s:?}"),
+        Expr::Variable { name, .. } => name.to_string(),
+        Expr::Grouping(inner) => format!("(group {})", self::expr(inner)),
+        Expr::Interpolation(parts) => parenthesize("interpolate", parts.iter().map(self::expr)),
+        Expr::Unary { op, right } => format!("({} {})", op.symbol(), self::expr(right)),
+        Expr::Get { object, name } => format!("(. {} {name})", self::expr(object)),
+        Expr::Binary { left, op, right } => {
+            format!(
+                "({} {} {})",
+                op.symbol(),
+                self::expr(left),
+                self::expr(right)
+            )
+        }
+        Expr::Logical { left, op, right } => {
+            format!(
+                "({} {} {})",
+                op.symbol(),
+                self::expr(left),
+                self::expr(right)
+            )
+        }
+        Expr::Assign { target, op, expr } => format!(
+            "({}= {} {})",
+            op.map_or("", |op| op.symbol()),
+            self::target(target),
+            self::expr(expr)
+        ),
+        Expr::Update {
+            target,
+            op,
+            prefix: true,
+        } => format!("({} {})", op.symbol(), self::target(target)),
+        Expr::Update {
+            target,
+            op,
+            prefix: false,
+        } => format!("(post{} {})", op.symbol(), self::target(target)),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => format!(
+            "(?: {} {} {})",
+            self::expr(condition),
+            self::expr(then_branch),
+            self::expr(else_branch)
+        ),
+    }
+}
+
+fn target(target: &LValue) -> String {
+    match target {
+        LValue::Variable { name, .. } => name.to_string(),
+    }
+}
+
+fn parenthesize(name: &str, parts: impl Iterator<Item = String>) -> String {
+    let mut out = format!("({name}");
+    for part in parts {
+        out.push(' ');
+        out.push_str(&part);
+    }
+    out.push(')');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Scanner;
+    use crate::parse::Parser;
+
+    fn print(source: &str) -> String {
+        program(&Parser::new(Scanner::new(source)).parse().unwrap())
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(print("-123 * (45.67);"), "(; (* (- 123) (group 45.67)))\n");
+        assert_eq!(
+            print(r#"a = !b or "x" == nil;"#),
+            "(; (= a (or (! b) (== \"x\" nil))))\n"
+        );
+        assert_eq!(print("a ? b : c ? 1 : 2;"), "(; (?: a b (?: c 1 2)))\n");
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            print("var a; var b = 1; { print a; } if (a) b; else {}"),
+            "(var a)\n(var b 1)\n(block (print a))\n(if a (; b) (block))\n"
+        );
+        assert_eq!(
+            print("while (true) print 1; for (;;) {}"),
+            "(while true (print 1))\n(for () () () (block))\n"
+        );
+        assert_eq!(
+            print("for (var i = 0; i < 2; i = i + 1) print i;"),
+            "(for (var i 0) (< i 2) (= i (+ i 1)) (print i))\n"
+        );
+        assert_eq!(
+            print(r#"import "a.lox"; import "b" as c;"#),
+            "(import \"a.lox\")\n(import \"b\" as c)\n"
+        );
+        assert_eq!(
+            print("try { throw e.message; } catch (e) {} finally {}"),
+            "(try (block (throw (. e message))) (catch e (block)) (finally (block)))\n"
+        );
+    }
+}
@@ -1,7 +1,15 @@
-use super::Expr;
+use super::{Expr, Symbol};
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
-pub enum Stmt {
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt {
+    /// The source line the statement starts on.
+    pub line: usize,
+    pub kind: StmtKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind {
     Expression(Expr),
     If {
         condition: Expr,
@@ -10,12 +18,93 @@ pub enum Stmt {
     },
     Print(Expr),
     Var {
-        name: String,
+        name: Symbol,
         initializer: Option<Expr>,
+        /// The text of the `///` comments just before the declaration.
+        doc: Option<String>,
     },
     Block(Vec<Stmt>),
+    /// `import "path";` or `import "path" as name;`.  Without `as`, the
+    /// module is bound to its file name, eg `lib` for `"util/lib.lox"`.
+    Import {
+        path: Rc<str>,
+        name: Symbol,
+        /// Whether `name` was given with `as`.
+        alias: bool,
+    },
     While {
         condition: Expr,
         body: Box<Stmt>,
     },
+    /// `throw value;`
+    Throw(Expr),
+    /// `try { } catch (name) { } finally { }`, with at least one of `catch`
+    /// and `finally`.  Each body is a `Block`.
+    Try {
+        body: Box<Stmt>,
+        catch: Option<(Symbol, Box<Stmt>)>,
+        finally: Option<Box<Stmt>>,
+    },
+    /// Surface syntax only: `lower` rewrites this into `Block`s and a `While`.
+    For {
+        initializer: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        increment: Option<Expr>,
+        body: Box<Stmt>,
+    },
+}
+
+impl Stmt {
+    pub fn new(line: usize, kind: StmtKind) -> Self {
+        Self { line, kind }
+    }
+
+    pub fn expression(line: usize, expr: Expr) -> Self {
+        Self::new(line, StmtKind::Expression(expr))
+    }
+
+    pub fn print(line: usize, expr: Expr) -> Self {
+        Self::new(line, StmtKind::Print(expr))
+    }
+
+    pub fn var(line: usize, name: impl Into<Symbol>, initializer: Option<Expr>) -> Self {
+        Self::new(
+            line,
+            StmtKind::Var {
+                name: name.into(),
+                initializer,
+                doc: None,
+            },
+        )
+    }
+
+    pub fn block(line: usize, stmts: Vec<Stmt>) -> Self {
+        Self::new(line, StmtKind::Block(stmts))
+    }
+
+    pub fn if_else(
+        line: usize,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Self {
+        Self::new(
+            line,
+            StmtKind::If {
+                condition,
+                then_branch: Box::new(then_branch),
+                else_branch: else_branch.map(Box::new),
+            },
+        )
+    }
+
+    pub fn while_loop(line: usize, condition: Expr, body: Stmt) -> Self {
+        Self::new(
+            line,
+            StmtKind::While {
+                condition,
+                body: Box::new(body),
+            },
+        )
+    }
 }
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+/// An interned identifier.  Symbols compare and hash by address, so looking
+/// up a variable doesn't touch the bytes of its name, and reading the name
+/// doesn't need the interner.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+/// A map keyed by symbols, which skips hashing the already-unique addresses.
+pub type SymbolMap<V> = HashMap<Symbol, V, BuildHasherDefault<SymbolHasher>>;
+
+#[derive(Default)]
+pub struct SymbolHasher(u64);
+
+impl Hasher for SymbolHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | u64::from(*byte);
+        }
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        // Spread nearby addresses over the high bits that pick a bucket.
+        self.0 = (n as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+/// Names are held weakly, so a name is freed once no symbol uses it.  The
+/// language server parses every keystroke, and the partial identifiers it
+/// sees would otherwise live forever.
+#[derive(Default)]
+struct Interner {
+    names: HashMap<Box<str>, Weak<str>>,
+    // Entries at the last sweep for dead names.
+    swept: usize,
+}
+
+// Shared by every thread so that symbols can move between them.
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        let mut interner = interner().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(symbol) = interner.names.get(name).and_then(Weak::upgrade) {
+            return Symbol(symbol);
+        }
+        if interner.names.len() >= 2 * interner.swept.max(64) {
+            interner.names.retain(|_, weak| weak.strong_count() > 0);
+            interner.swept = interner.names.len();
+        }
+        let symbol: Arc<str> = name.into();
+        interner.names.insert(name.into(), Arc::downgrade(&symbol));
+        Symbol(symbol)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(Arc::as_ptr(&self.0) as *const u8 as usize);
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let a = Symbol::intern("alpha");
+        assert_eq!(a, Symbol::intern("alpha"));
+        assert_ne!(a, Symbol::intern("beta"));
+        assert_eq!(a.as_str(), "alpha");
+        assert_eq!(a, "alpha");
+        assert_eq!(format!("{a} {a:?}"), "alpha \"alpha\"");
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let a = Symbol::intern("shared");
+        let b = std::thread::spawn(|| Symbol::intern("shared"))
+            .join()
+            .unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_unused_names_are_freed() {
+        let weak = Arc::downgrade(&Symbol::intern("transient").0);
+        assert!(weak.upgrade().is_none());
+        let a = Symbol::intern("transient");
+        assert_eq!(a, Symbol::intern("transient"));
+    }
+}
@@ -0,0 +1,532 @@
+//! Traversals of the syntax tree.
+//!
+//! `Visitor` and `VisitorMut` walk a tree by reference, and `Fold` rebuilds
+//! it by value.  Each trait method defaults to the matching `walk_*` or
+//! `fold_*` function, which visits the node's children in source order, so
+//! an implementation only overrides the nodes it cares about and calls back
+//! into the walk function to keep descending.  Assignment targets have
+//! their own `lvalue` methods.
+//!
+//! Adding a node variant still means touching every exhaustive match on
+//! `StmtKind` or `Expr`, which the compiler will point out:
+//!
+//! - `walk_*`, `walk_*_mut` and `fold_*` here, which are separate matches;
+//! - `json` (encoding and decoding) and `sexpr` in this module;
+//! - the interpreter, and the node names in `interpreter::profile`;
+//! - `is_constant` in the linter.
+//!
+//! Code that falls back to a walk or a default for other nodes compiles
+//! unchanged but may need a case for the new one: `lower`, the resolver,
+//! the linter (including `terminates`), the language server's analysis and
+//! `interpreter::coverage`.
+use super::{Expr, LValue, Stmt, StmtKind};
+
+pub trait Visitor: Sized {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_lvalue(&mut self, target: &LValue) {
+        walk_lvalue(self, target);
+    }
+}
+
+pub fn walk_stmt<V: Visitor>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Expression(expr) | StmtKind::Print(expr) | StmtKind::Throw(expr) => {
+            visitor.visit_expr(expr)
+        }
+        StmtKind::Var { initializer, .. } => {
+            if let Some(init) = initializer {
+                visitor.visit_expr(init);
+            }
+        }
+        StmtKind::Block(stmts) => stmts.iter().for_each(|stmt| visitor.visit_stmt(stmt)),
+        StmtKind::Import { .. } => (),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => {
+            if let Some(init) = initializer {
+                visitor.visit_stmt(init);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expr(condition);
+            }
+            if let Some(incr) = increment {
+                visitor.visit_expr(incr);
+            }
+            visitor.visit_stmt(body);
+        }
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_stmt(body);
+            if let Some((_, handler)) = catch {
+                visitor.visit_stmt(handler);
+            }
+            if let Some(finally) = finally {
+                visitor.visit_stmt(finally);
+            }
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Literal(_) | Expr::Variable { .. } => (),
+        Expr::Grouping(inner)
+        | Expr::Unary { right: inner, .. }
+        | Expr::Get { object: inner, .. } => visitor.visit_expr(inner),
+        Expr::Assign { target, expr, .. } => {
+            visitor.visit_lvalue(target);
+            visitor.visit_expr(expr);
+        }
+        Expr::Update { target, .. } => visitor.visit_lvalue(target),
+        Expr::Interpolation(parts) => parts.iter().for_each(|part| visitor.visit_expr(part)),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_branch);
+            visitor.visit_expr(else_branch);
+        }
+    }
+}
+
+pub fn walk_lvalue<V: Visitor>(_visitor: &mut V, target: &LValue) {
+    match target {
+        LValue::Variable { .. } => (),
+    }
+}
+
+pub trait VisitorMut: Sized {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_lvalue_mut(&mut self, target: &mut LValue) {
+        walk_lvalue_mut(self, target);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(visitor: &mut V, stmt: &mut Stmt) {
+    match &mut stmt.kind {
+        StmtKind::Expression(expr) | StmtKind::Print(expr) | StmtKind::Throw(expr) => {
+            visitor.visit_expr_mut(expr)
+        }
+        StmtKind::Var { initializer, .. } => {
+            if let Some(init) = initializer {
+                visitor.visit_expr_mut(init);
+            }
+        }
+        StmtKind::Block(stmts) => stmts
+            .iter_mut()
+            .for_each(|stmt| visitor.visit_stmt_mut(stmt)),
+        StmtKind::Import { .. } => (),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt_mut(else_branch);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+        }
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => {
+            if let Some(init) = initializer {
+                visitor.visit_stmt_mut(init);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expr_mut(condition);
+            }
+            if let Some(incr) = increment {
+                visitor.visit_expr_mut(incr);
+            }
+            visitor.visit_stmt_mut(body);
+        }
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_stmt_mut(body);
+            if let Some((_, handler)) = catch {
+                visitor.visit_stmt_mut(handler);
+            }
+            if let Some(finally) = finally {
+                visitor.visit_stmt_mut(finally);
+            }
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Literal(_) | Expr::Variable { .. } => (),
+        Expr::Grouping(inner)
+        | Expr::Unary { right: inner, .. }
+        | Expr::Get { object: inner, .. } => visitor.visit_expr_mut(inner),
+        Expr::Assign { target, expr, .. } => {
+            visitor.visit_lvalue_mut(target);
+            visitor.visit_expr_mut(expr);
+        }
+        Expr::Update { target, .. } => visitor.visit_lvalue_mut(target),
+        Expr::Interpolation(parts) => parts
+            .iter_mut()
+            .for_each(|part| visitor.visit_expr_mut(part)),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(then_branch);
+            visitor.visit_expr_mut(else_branch);
+        }
+    }
+}
+
+pub fn walk_lvalue_mut<V: VisitorMut>(_visitor: &mut V, target: &mut LValue) {
+    match target {
+        LValue::Variable { .. } => (),
+    }
+}
+
+pub trait Fold: Sized {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_lvalue(&mut self, target: LValue) -> LValue {
+        fold_lvalue(self, target)
+    }
+}
+
+/// Rebuild `stmt` from its children, each folded by `folder`.
+pub fn fold_stmt<F: Fold>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        kind @ StmtKind::Import { .. } => kind,
+        StmtKind::Expression(expr) => StmtKind::Expression(folder.fold_expr(expr)),
+        StmtKind::Print(expr) => StmtKind::Print(folder.fold_expr(expr)),
+        StmtKind::Throw(expr) => StmtKind::Throw(folder.fold_expr(expr)),
+        StmtKind::Var {
+            name,
+            initializer,
+            doc,
+        } => StmtKind::Var {
+            name,
+            initializer: initializer.map(|init| folder.fold_expr(init)),
+            doc,
+        },
+        StmtKind::Block(stmts) => StmtKind::Block(
+            stmts
+                .into_iter()
+                .map(|stmt| folder.fold_stmt(stmt))
+                .collect(),
+        ),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => StmtKind::If {
+            condition: folder.fold_expr(condition),
+            then_branch: Box::new(folder.fold_stmt(*then_branch)),
+            else_branch: else_branch.map(|stmt| Box::new(folder.fold_stmt(*stmt))),
+        },
+        StmtKind::While { condition, body } => StmtKind::While {
+            condition: folder.fold_expr(condition),
+            body: Box::new(folder.fold_stmt(*body)),
+        },
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => StmtKind::For {
+            initializer: initializer.map(|stmt| Box::new(folder.fold_stmt(*stmt))),
+            condition: condition.map(|expr| folder.fold_expr(expr)),
+            increment: increment.map(|expr| folder.fold_expr(expr)),
+            body: Box::new(folder.fold_stmt(*body)),
+        },
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => StmtKind::Try {
+            body: Box::new(folder.fold_stmt(*body)),
+            catch: catch.map(|(name, handler)| (name, Box::new(folder.fold_stmt(*handler)))),
+            finally: finally.map(|stmt| Box::new(folder.fold_stmt(*stmt))),
+        },
+    };
+    Stmt::new(stmt.line, kind)
+}
+
+/// Rebuild `expr` from its children, each folded by `folder`.
+pub fn fold_expr<F: Fold>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Literal(_) | Expr::Variable { .. } => expr,
+        Expr::Grouping(inner) => Expr::group(folder.fold_expr(*inner)),
+        Expr::Interpolation(parts) => Expr::Interpolation(
+            parts
+                .into_iter()
+                .map(|part| folder.fold_expr(part))
+                .collect(),
+        ),
+        Expr::Unary { op, right } => Expr::unary(op, folder.fold_expr(*right)),
+        Expr::Get { object, name } => Expr::get(folder.fold_expr(*object), name),
+        Expr::Assign { target, op, expr } => Expr::Assign {
+            target: folder.fold_lvalue(target),
+            op,
+            expr: Box::new(folder.fold_expr(*expr)),
+        },
+        Expr::Update { target, op, prefix } => Expr::Update {
+            target: folder.fold_lvalue(target),
+            op,
+            prefix,
+        },
+        Expr::Binary { left, op, right } => {
+            let left = folder.fold_expr(*left);
+            Expr::binary(left, op, folder.fold_expr(*right))
+        }
+        Expr::Logical { left, op, right } => {
+            let left = folder.fold_expr(*left);
+            Expr::logical(left, op, folder.fold_expr(*right))
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let condition = folder.fold_expr(*condition);
+            let then_branch = folder.fold_expr(*then_branch);
+            Expr::conditional(condition, then_branch, folder.fold_expr(*else_branch))
+        }
+    }
+}
+
+/// Rebuild `target` from its children, each folded by `folder`.
+pub fn fold_lvalue<F: Fold>(_folder: &mut F, target: LValue) -> LValue {
+    match target {
+        LValue::Variable { .. } => target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Scanner;
+    use crate::parse::{BinaryOp, LValue, Literal, Parser, Symbol};
+
+    const SOURCE: &str = "var a = 1;
+{
+  var b = a + 2;
+  a = b * (a - 3);
+}
+for (var i = 0; i < a; i = i + 1) if (i == 2) print i or b;";
+
+    fn parse() -> Vec<Stmt> {
+        Parser::new(Scanner::new(SOURCE)).parse().unwrap()
+    }
+
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            if let StmtKind::Var { name, .. } = &stmt.kind {
+                self.0.push(name.to_string());
+            }
+            walk_stmt(self, stmt);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Variable { name, .. } = expr {
+                self.0.push(name.to_string());
+            }
+            walk_expr(self, expr);
+        }
+
+        fn visit_lvalue(&mut self, target: &LValue) {
+            let LValue::Variable { name, .. } = target;
+            self.0.push(name.to_string());
+        }
+    }
+
+    #[derive(Default)]
+    struct Count {
+        stmts: usize,
+        exprs: usize,
+    }
+
+    impl Visitor for Count {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            self.stmts += 1;
+            walk_stmt(self, stmt);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            self.exprs += 1;
+            walk_expr(self, expr);
+        }
+    }
+
+    struct Rename<'a>(&'a str, &'a str);
+
+    impl VisitorMut for Rename<'_> {
+        fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+            if let StmtKind::Var { name, .. } = &mut stmt.kind {
+                if *name == self.0 {
+                    *name = Symbol::intern(self.1);
+                }
+            }
+            walk_stmt_mut(self, stmt);
+        }
+
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            if let Expr::Variable { name, .. } = expr {
+                if *name == self.0 {
+                    *name = Symbol::intern(self.1);
+                }
+            }
+            walk_expr_mut(self, expr);
+        }
+
+        fn visit_lvalue_mut(&mut self, target: &mut LValue) {
+            let LValue::Variable { name, .. } = target;
+            if *name == self.0 {
+                *name = Symbol::intern(self.1);
+            }
+        }
+    }
+
+    /// Folds additions of two number literals.
+    struct ConstantAdd;
+
+    impl Fold for ConstantAdd {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match fold_expr(self, expr) {
+                Expr::Binary {
+                    left,
+                    op: BinaryOp::Add,
+                    right,
+                } => match (*left, *right) {
+                    (Expr::Literal(Literal::Number(l)), Expr::Literal(Literal::Number(r))) => {
+                        Expr::number(l + r)
+                    }
+                    (left, right) => Expr::binary(left, BinaryOp::Add, right),
+                },
+                expr => expr,
+            }
+        }
+    }
+
+    /// Renames assignment targets only.
+    struct RenameTargets<'a>(&'a str, &'a str);
+
+    impl Fold for RenameTargets<'_> {
+        fn fold_lvalue(&mut self, target: LValue) -> LValue {
+            match target {
+                LValue::Variable { name, .. } if name == self.0 => LValue::var(self.1),
+                target => target,
+            }
+        }
+    }
+
+    fn names(stmts: &[Stmt]) -> Vec<String> {
+        let mut names = Names::default();
+        stmts.iter().for_each(|stmt| names.visit_stmt(stmt));
+        names.0
+    }
+
+    #[test]
+    fn test_visitor() {
+        assert_eq!(
+            names(&parse()),
+            vec!["a", "b", "a", "a", "b", "a", "i", "i", "a", "i", "i", "i", "i", "b"]
+        );
+
+        let mut count = Count::default();
+        parse().iter().for_each(|stmt| count.visit_stmt(stmt));
+        assert_eq!((count.stmts, count.exprs), (8, 25));
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut stmts = parse();
+        stmts
+            .iter_mut()
+            .for_each(|stmt| Rename("i", "j").visit_stmt_mut(stmt));
+        let names = names(&stmts);
+        assert!(!names.contains(&"i".to_owned()));
+        assert_eq!(names.iter().filter(|name| *name == "j").count(), 6);
+    }
+
+    #[test]
+    fn test_fold() {
+        let parse = |source| Parser::new(Scanner::new(source)).parse().unwrap();
+        let folded: Vec<Stmt> = parse("print (1 + 2) + a; if (x) y = 3 + 4;")
+            .into_iter()
+            .map(|stmt| ConstantAdd.fold_stmt(stmt))
+            .collect();
+        assert_eq!(folded, parse("print (3) + a; if (x) y = 7;"));
+
+        let renamed: Vec<Stmt> = parse("a = a + 1; a++; --a; print a;")
+            .into_iter()
+            .map(|stmt| RenameTargets("a", "b").fold_stmt(stmt))
+            .collect();
+        assert_eq!(renamed, parse("b = a + 1; b++; --b; print a;"));
+    }
+}
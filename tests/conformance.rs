@@ -0,0 +1,213 @@
+//! Conformance tests in the format of the reference Lox test suite.
+//!
+//! Every `.lox` file under `tests/lox` is run through `Lox` with its output
+//! captured.  Expectations are written as comments in the script itself:
+//!
+//! - `// expect: <line>` -- the next line `print` should produce.
+//! - `// expect runtime error: <message>` -- the script must stop with this
+//!   runtime error, after producing any expected output.
+//! - `// [line N] Error` or `// [line N] Error: <message>` -- the script must
+//!   fail to parse on line N, with an error that contains `<message>`.
+//!
+//! Imports are found relative to the script's directory.
+//!
+//! All files are run, and the failures are reported together with a
+//! per-file diff of expected and actual results.
+use lox::interpreter::FileLoader;
+use lox::{Lox, LoxError};
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A `Write` sink that can be read back after the interpreter is done with it.
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.0.borrow())
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Expectations {
+    output: Vec<String>,
+    runtime_error: Option<String>,
+    parse_error: Option<(usize, String)>,
+}
+
+fn parse_expectations(source: &str) -> Expectations {
+    let mut expected = Expectations::default();
+    for line in source.lines() {
+        // The last marker on the line, so that a `//` inside a string
+        // literal doesn't start the comment.
+        let Some(start) = ["// expect", "// [line "]
+            .iter()
+            .filter_map(|marker| line.rfind(marker))
+            .max()
+        else {
+            continue;
+        };
+        let comment = line[start + 2..].trim();
+        if let Some(output) = comment.strip_prefix("expect:") {
+            expected.output.push(output.trim().to_owned());
+        } else if let Some(message) = comment.strip_prefix("expect runtime error:") {
+            expected.runtime_error = Some(message.trim().to_owned());
+        } else if let Some(rest) = comment.strip_prefix("[line ") {
+            let (line_num, rest) = rest
+                .split_once(']')
+                .unwrap_or_else(|| panic!("Malformed error expectation: {comment}"));
+            let line_num = line_num
+                .parse()
+                .unwrap_or_else(|_| panic!("Malformed error line: {comment}"));
+            let message = rest
+                .trim()
+                .trim_start_matches("Error")
+                .trim_start_matches(':');
+            expected.parse_error = Some((line_num, message.trim().to_owned()));
+        }
+    }
+    expected
+}
+
+/// Run one script, returning a description of each mismatch.
+fn check_file(path: &Path) -> Vec<String> {
+    let source = std::fs::read_to_string(path).expect("Unable to read test file");
+    let expected = parse_expectations(&source);
+    let captured = Captured::default();
+    let mut lox = Lox::with_output(Box::new(captured.clone()));
+    let dir = path.parent().expect("Test files are in a directory");
+    lox.set_module_loader(Box::new(FileLoader::new(vec![dir.to_path_buf()])));
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    lox.set_main_module(file_name.expect("Test files have UTF-8 names"))
+        .expect("Test files can be imported");
+    let result = lox.run(&source);
+
+    let mut problems = Vec::new();
+    let actual = captured.lines();
+    let num_lines = expected.output.len().max(actual.len());
+    for idx in 0..num_lines {
+        match (expected.output.get(idx), actual.get(idx)) {
+            (Some(exp), Some(act)) if exp == act => (),
+            (exp, act) => {
+                if let Some(exp) = exp {
+                    problems.push(format!("- {exp}"));
+                }
+                if let Some(act) = act {
+                    problems.push(format!("+ {act}"));
+                }
+            }
+        }
+    }
+
+    match (&expected, result) {
+        (
+            Expectations {
+                parse_error: Some((line, message)),
+                ..
+            },
+            Err(LoxError::Parse(err)),
+        ) => {
+            if err.line() != Some(*line) || !err.to_string().contains(message.as_str()) {
+                problems.push(format!("- [line {line}] Error: {message}"));
+                match err.line() {
+                    Some(actual_line) => {
+                        problems.push(format!("+ [line {actual_line}] Error: {err}"))
+                    }
+                    None => problems.push(format!("+ Error: {err}")),
+                }
+            }
+        }
+        (
+            Expectations {
+                runtime_error: Some(message),
+                ..
+            },
+            Err(LoxError::Runtime(err)),
+        ) => {
+            if &err.to_string() != message {
+                problems.push(format!("- runtime error: {message}"));
+                problems.push(format!("+ runtime error: {err}"));
+            }
+        }
+        (
+            Expectations {
+                parse_error: None,
+                runtime_error: None,
+                ..
+            },
+            Ok(()),
+        ) => (),
+        (expected, actual) => {
+            if let Some((line, message)) = &expected.parse_error {
+                problems.push(format!("- [line {line}] Error: {message}"));
+            }
+            if let Some(message) = &expected.runtime_error {
+                problems.push(format!("- runtime error: {message}"));
+            }
+            match actual {
+                Ok(()) => problems.push("+ (no error)".to_owned()),
+                Err(err) => problems.push(format!("+ {err}")),
+            }
+        }
+    }
+    problems
+}
+
+fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("Unable to read test directory")
+        .map(|entry| entry.expect("Unable to read directory entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_scripts(&path, scripts);
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            scripts.push(path);
+        }
+    }
+}
+
+#[test]
+fn test_conformance_suite() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("lox");
+    let mut scripts = Vec::new();
+    collect_scripts(&root, &mut scripts);
+    assert!(!scripts.is_empty(), "No scripts found in {root:?}");
+
+    let mut report = String::new();
+    let mut failures = 0;
+    for path in &scripts {
+        let problems = check_file(path);
+        if !problems.is_empty() {
+            failures += 1;
+            let name = path.strip_prefix(&root).unwrap_or(path);
+            report.push_str(&format!("{}:\n", name.display()));
+            for problem in problems {
+                report.push_str(&format!("    {problem}\n"));
+            }
+        }
+    }
+    assert!(
+        failures == 0,
+        "{failures} of {} scripts failed:\n{report}",
+        scripts.len()
+    );
+}
@@ -0,0 +1,102 @@
+//! Debug adapter tests replayed from recorded transcripts.
+//!
+//! Each `.json` file under `tests/dap` is an array of steps.  A `{"send": ..}`
+//! step is a message from the client; an `{"expect": ..}` step is the next
+//! message the adapter must send.  All client messages are sent up front, so
+//! transcripts must be written in the order a real session would happen.
+//! Expected messages only need to contain a subset of the actual fields, so
+//! `seq` numbers and uninteresting fields can be left out.
+use lox::debug::dap::serve;
+use lox::lsp::{read_message, write_message};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Whether every field in `expected` has the same value in `actual`.
+fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|a| matches(value, a))),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().zip(actual).all(|(e, a)| matches(e, a))
+        }
+        _ => expected == actual,
+    }
+}
+
+fn run_transcript(path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let steps: Vec<Value> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+    let mut input = Vec::new();
+    let mut expected = Vec::new();
+    for step in steps {
+        if let Some(message) = step.get("send") {
+            write_message(&mut input, message).unwrap();
+        } else if let Some(message) = step.get("expect") {
+            expected.push(message.clone());
+        }
+    }
+
+    let output = Captured::default();
+    serve(Cursor::new(input), output.clone()).map_err(|e| e.to_string())?;
+    let mut reader = Cursor::new(output.0.take());
+    let mut actual = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        actual.push(message.map_err(|e| e.to_string())?);
+    }
+
+    for (idx, expected) in expected.iter().enumerate() {
+        match actual.get(idx) {
+            Some(message) if matches(expected, message) => (),
+            Some(message) => {
+                return Err(format!(
+                    "message {idx}: expected {expected}\n  got {message}"
+                ))
+            }
+            None => return Err(format!("message {idx}: expected {expected}, got nothing")),
+        }
+    }
+    if actual.len() > expected.len() {
+        return Err(format!("unexpected message {}", actual[expected.len()]));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dap_transcripts() {
+    let mut paths: Vec<_> = std::fs::read_dir("tests/dap")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            run_transcript(path)
+                .err()
+                .map(|err| format!("{}: {err}", path.display()))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}